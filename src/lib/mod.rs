@@ -72,12 +72,15 @@ extern crate indexmap;
 #[macro_use]
 extern crate serde;
 
+mod span;
 mod u32struct;
 pub mod yacc;
 
+pub use span::Span;
+
 /// A type specifically for nonterminal indices.
 pub use u32struct::NTIdx;
-pub use u32struct::{PIdx, SIdx, TIdx};
+pub use u32struct::{PIdx, SIdx, StIdx, TIdx};
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -53,7 +53,7 @@ macro_rules! u32struct {
 
         impl From<usize> for $n {
             fn from(v: usize) -> Self {
-                if v > usize::from($t::max_value()) {
+                if v > $t::max_value() as usize {
                     panic!("Overflow");
                 }
                 $n{v: v as $t}
@@ -78,7 +78,8 @@ macro_rules! u32struct {
 // production, or terminals? Yes, now that I've said it out loud, they probably will. But all
 // practical grammars I know of are comfortably within these limits, so use narrow storage types
 // for now, knowing that we can transparently move the storage type from u16 to u32 in the future
-// without changing the user visible API.
+// without changing the user visible API. State graphs are another matter: large grammars can
+// easily have more than 65535 states, so state indices are stored as u32s.
 
 u32struct!(
     /// A type specifically for nonterminal indices.
@@ -97,3 +98,20 @@ u32struct!(
     /// A type specifically for token indices.
     TIdx,
     u16);
+u32struct!(
+    /// A type specifically for state indices (e.g. in an LR state graph).
+    StIdx,
+    u32);
+
+#[cfg(test)]
+mod test {
+    use super::StIdx;
+
+    #[test]
+    fn test_stidx_u32() {
+        for &i in &[65535u32, 65536, u32::MAX] {
+            assert_eq!(u32::from(StIdx::from(i)), i);
+            assert_eq!(usize::from(StIdx::from(i as usize)), i as usize);
+        }
+    }
+}
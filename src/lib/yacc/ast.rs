@@ -33,21 +33,84 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
+extern crate regex;
+use self::regex::{Captures, Regex};
+
+use Span;
 use yacc::Precedence;
+use yacc::parser::YaccParserWarning;
+
+/// The name by which productions refer to Yacc's built-in `error` terminal, which is used for
+/// error recovery. The parser represents such references as `Symbol::Error`, so a quoted `'error'`
+/// is an ordinary terminal.
+pub(crate) const ERROR_TERM: &str = "error";
+
+lazy_static! {
+    // Matches either a quoted terminal or a name within the name of a synthetic rule
+    static ref RE_SYNTHETIC_NAME_PART: Regex = {
+        Regex::new(r"'(?:[^'\\]|\\.)*'|[\p{XID_Start}_]\p{XID_Continue}*").unwrap()
+    };
+}
 
 /// An AST representing a grammar. This is built up gradually: when it is finished, the
 /// `complete_and_validate` must be called exactly once in order to finish the set-up. At that
 /// point, any further mutations made to the struct lead to undefined behaviour.
+#[derive(Clone)]
 pub struct GrammarAST {
     pub start: Option<String>,
+    // any further start rules given by %start (e.g. `b` and `c` in `%start a b c`), each of which
+    // is an additional entry point into the grammar
+    pub extra_starts: Vec<String>,
     // map from a rule name to indexes into prods
     pub rules: IndexMap<String, Vec<usize>>,
+    // map from a rule name to the span of its name where the rule is first defined
+    pub rule_spans: HashMap<String, Span>,
     pub prods: Vec<Production>,
-    pub tokens: HashSet<String>,
+    // the names of rules synthesised from EBNF expressions (e.g. `X*`): each such rule's span in
+    // rule_spans is that of the EBNF expression it was synthesised from
+    pub synthetic_rules: HashSet<String>,
+    // map from the name of a parameterised rule (e.g. `list(X): ...`) to its parameters' names.
+    // Parameterised rules are kept in `rules` until complete_and_validate expands them.
+    pub templates: HashMap<String, Vec<String>>,
+    // map from the name of an application of a parameterised rule (e.g. `list('a')`), as used in
+    // productions, to the parameterised rule's name and the arguments it is applied to. Synthetic
+    // rules which refer to the parameters of the rule they occur in are themselves parameterised
+    // (by those parameters): their applications are named as per the synthetic rule, with the
+    // parameters replaced by the arguments (e.g. `X*` applied to 'a' is named `'a'*`).
+    pub applications: HashMap<String, (String, Vec<Symbol>)>,
+    // the names of rules annotated with %inline: these are substituted into the productions which
+    // reference them when a YaccGrammar is created, rather than becoming nonterminals
+    pub inline_rules: HashSet<String>,
+    // the set of tokens, in the order they were first declared or used (this order determines the
+    // `TIdx` of each terminal in a `YaccGrammar`)
+    pub tokens: IndexSet<String>,
+    // map from a token name to the span where it is first declared (or, for tokens which are
+    // implicitly declared by being used in a production, first used)
+    pub token_spans: HashMap<String, Span>,
+    // map from a token name to the number explicitly given to it by %token (if any)
+    pub token_numbers: HashMap<String, u32>,
+    // map from a token name to the alias given to it by %token NAME "alias" (if any)
+    pub token_aliases: HashMap<String, String>,
     pub precs: HashMap<String, Precedence>,
-    pub implicit_tokens: Option<HashSet<String>>
+    // map from a token name to the span of the token in its %left/%right/%nonassoc declaration
+    pub prec_spans: HashMap<String, Span>,
+    pub implicit_tokens: Option<IndexSet<String>>,
+    // the number of shift/reduce conflicts specified by %expect (if any)
+    pub expect: Option<usize>,
+    // the number of reduce/reduce conflicts specified by %expect-rr (if any)
+    pub expectrr: Option<usize>,
+    // the contents of each %{ ... %} block, in the order they were found
+    pub prologues: Vec<String>,
+    // the contents of the %union { ... } declaration (if any)
+    pub union: Option<String>,
+    // map from a symbol name to the type given to it by %type or %token <T>
+    pub types: IndexMap<String, String>,
+    // the programs section (everything after the second %%) and its offset in the source, if any
+    pub programs: Option<(usize, String)>,
+    // warnings from any lints enabled in the YaccOptions the grammar was parsed with
+    pub warnings: Vec<YaccParserWarning>
 }
 
 #[derive(Debug)]
@@ -56,16 +119,33 @@ pub struct Rule {
     pub prod_idxs: Vec<usize> // index into GrammarAST.prod
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Production {
     pub symbols: Vec<Symbol>,
-    pub precedence: Option<String>
+    pub precedence: Option<String>,
+    // the production's action code (excluding the outer braces), if any
+    pub action: Option<String>,
+    // the span of the entire production (zero-length for empty productions without %empty)
+    pub span: Span,
+    // the span of each symbol in `symbols`
+    pub symbol_spans: Vec<Span>,
+    // the span of the token referenced by %prec (if any)
+    pub precedence_span: Option<Span>,
+    // the span of the %empty marker, if this production was explicitly marked as empty
+    pub empty_span: Option<Span>,
+    // the label (if any) of each symbol in `symbols` (e.g. `lhs` in `lhs=expr` or `expr[lhs]`)
+    pub labels: Vec<Option<String>>,
+    // the production's name, if any (e.g. `Add` in `expr: expr '+' term #Add`)
+    pub name: Option<String>
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub enum Symbol {
     Nonterm(String),
-    Term(String)
+    Term(String),
+    /// Yacc's built-in `error` terminal, which is never added to `GrammarAST::tokens`: instead,
+    /// `YaccGrammar` adds it as a terminal of its own.
+    Error
 }
 
 /// The various different possible grammar validation errors.
@@ -75,14 +155,26 @@ pub enum GrammarValidationErrorKind {
     InvalidStartRule,
     UnknownRuleRef,
     UnknownToken,
-    NoPrecForToken
+    NoPrecForToken,
+    UnknownTypeSymbol,
+    WrongNumberOfArguments,
+    ParameterShadowsSymbol,
+    NonTerminatingExpansion,
+    InlineStartRule,
+    RecursiveInlineRule,
+    InlineRuleAction,
+    DuplicateLabel,
+    DuplicateProductionName
 }
 
 /// `GrammarAST` validation errors return an instance of this struct.
 #[derive(Debug)]
 pub struct GrammarValidationError {
     pub kind: GrammarValidationErrorKind,
-    pub sym: Option<Symbol>
+    pub sym: Option<Symbol>,
+    // the span in the source grammar of the construct the error refers to (e.g. the offending
+    // symbol in a production, or the name of the offending rule), if known
+    pub span: Option<Span>
 }
 
 impl fmt::Display for GrammarValidationError {
@@ -102,6 +194,33 @@ impl fmt::Display for GrammarValidationError {
             },
             GrammarValidationErrorKind::NoPrecForToken => {
                 write!(f, "Token '{}' used in %prec has no precedence attached", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::UnknownTypeSymbol => {
+                write!(f, "Unknown symbol '{}' in %type declaration", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::WrongNumberOfArguments => {
+                write!(f, "Rule '{}' applied to the wrong number of arguments", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::ParameterShadowsSymbol => {
+                write!(f, "Parameter '{}' has the same name as a rule or token", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::NonTerminatingExpansion => {
+                write!(f, "Expansion of rule '{}' does not terminate", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::InlineStartRule => {
+                write!(f, "Start rule '{}' cannot be %inline", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::RecursiveInlineRule => {
+                write!(f, "%inline rule '{}' is recursive", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::InlineRuleAction => {
+                write!(f, "%inline rule '{}' has an action", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::DuplicateLabel => {
+                write!(f, "Rule '{}' has a production with a duplicate label", self.sym.as_ref().unwrap())
+            },
+            GrammarValidationErrorKind::DuplicateProductionName => {
+                write!(f, "Rule '{}' has more than one production with the same name", self.sym.as_ref().unwrap())
             }
         }
     }
@@ -111,7 +230,8 @@ impl fmt::Display for Symbol {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Symbol::Nonterm(ref s) => write!(f, "{}", s),
-            Symbol::Term(ref s)    => write!(f, "{}", s)
+            Symbol::Term(ref s)    => write!(f, "{}", s),
+            Symbol::Error          => write!(f, "{}", ERROR_TERM)
         }
     }
 }
@@ -120,20 +240,52 @@ impl GrammarAST {
     pub fn new() -> GrammarAST {
         GrammarAST {
             start:  None,
+            extra_starts: Vec::new(),
             rules:  IndexMap::new(), // Using an IndexMap means that we retain the order
                                      // of rules as they're found in the input file.
+            rule_spans: HashMap::new(),
             prods:  Vec::new(),
-            tokens: HashSet::new(),
+            synthetic_rules: HashSet::new(),
+            templates: HashMap::new(),
+            applications: HashMap::new(),
+            inline_rules: HashSet::new(),
+            tokens: IndexSet::new(),
+            token_spans: HashMap::new(),
+            token_numbers: HashMap::new(),
+            token_aliases: HashMap::new(),
             precs:  HashMap::new(),
-            implicit_tokens: None
+            prec_spans: HashMap::new(),
+            implicit_tokens: None,
+            expect: None,
+            expectrr: None,
+            prologues: Vec::new(),
+            union: None,
+            types: IndexMap::new(),
+            programs: None,
+            warnings: Vec::new()
         }
     }
 
-    pub fn add_prod(&mut self, key: String, symbols: Vec<Symbol>, precedence: Option<String>) {
+    /// Add a production to the rule `key`. Since there is no source text to refer to, all of the
+    /// production's spans are zero-length spans at offset 0: `add_production` should be used by
+    /// callers which know the production's spans.
+    pub fn add_prod(&mut self, key: String, symbols: Vec<Symbol>, precedence: Option<String>,
+                    action: Option<String>) {
+        let symbol_spans = vec![Span::new(0, 0); symbols.len()];
+        let precedence_span = precedence.as_ref().map(|_| Span::new(0, 0));
+        let labels = vec![None; symbols.len()];
+        self.add_production(key, Production{symbols, precedence, action, span: Span::new(0, 0),
+                                            symbol_spans, precedence_span, empty_span: None,
+                                            labels, name: None});
+    }
+
+    /// Add the production `prod` to the rule `key`.
+    pub fn add_production(&mut self, key: String, prod: Production) {
+        debug_assert_eq!(prod.symbols.len(), prod.symbol_spans.len());
         self.rules.entry(key)
                   .or_insert_with(Vec::new)
                   .push(self.prods.len());
-        self.prods.push(Production{symbols, precedence});
+        self.prods.push(prod);
     }
 
     pub fn get_rule(&self, key: &str) -> Option<&Vec<usize>>{
@@ -144,66 +296,530 @@ impl GrammarAST {
         self.tokens.contains(s)
     }
 
-    /// After the AST has been populated, perform any final operations, and validate the grammar
-    /// checking that:
-    ///   1) The start rule references a rule in the grammar
-    ///   2) Every nonterminal reference references a rule in the grammar
-    ///   3) Every terminal reference references a declared token
-    ///   4) If a production has a precedence terminal, then it references a declared token
-    /// If the validation succeeds, None is returned.
-    pub(crate) fn complete_and_validate(&mut self) -> Result<(), GrammarValidationError> {
+    /// After the AST has been populated, perform any final operations (i.e. expanding
+    /// parameterised rules), and validate the grammar checking that:
+    ///   1) Every parameterised rule's parameters are distinct from the grammar's rules and
+    ///      tokens, every application of a parameterised rule has the right number of arguments,
+    ///      and expanding parameterised rules terminates
+    ///   2) The start rule(s) reference (non-parameterised) rules in the grammar
+    ///   3) Every nonterminal reference references a rule in the grammar
+    ///   4) Every terminal reference references a declared token
+    ///   5) If a production has a precedence terminal, then it references a declared token
+    ///   6) Every symbol given a type references a rule or a declared token
+    ///   7) Every %inline rule is not the start rule, is not recursive (via other %inline rules),
+    ///      and has no actions
+    ///   8) No production labels more than one symbol with the same label
+    ///   9) No two productions of a rule have the same name (though see `inline_prod`)
+    ///
+    /// If the validation succeeds, `Ok(())` is returned; otherwise every validation error found
+    /// is returned.
+    pub(crate) fn complete_and_validate(&mut self) -> Result<(), Vec<GrammarValidationError>> {
+        let mut errs = Vec::new();
+        self.expand_templates(&mut errs);
         match self.start {
             None => {
-                return Err(GrammarValidationError{kind: GrammarValidationErrorKind::NoStartRule,
-                                                  sym: None})
+                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::NoStartRule,
+                                                 sym: None, span: None});
             },
             Some(ref s) => {
-                if !self.rules.contains_key(s) {
-                    return Err(GrammarValidationError{kind: GrammarValidationErrorKind::InvalidStartRule,
-                                               sym: Some(Symbol::Nonterm(s.clone()))});
+                for s in Some(s).into_iter().chain(&self.extra_starts) {
+                    if !self.rules.contains_key(s) || self.templates.contains_key(s) {
+                        errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::InvalidStartRule,
+                                                         sym: Some(Symbol::Nonterm(s.clone())),
+                                                         span: None});
+                    }
                 }
             }
         }
-        for prod_idxs in self.rules.values() {
+        for (name, prod_idxs) in &self.rules {
+            // If expansion failed, parameterised rules are still present, but can't be sensibly
+            // validated.
+            if self.templates.contains_key(name) {
+                continue;
+            }
             for &prod_idx in prod_idxs {
                 let prod = &self.prods[prod_idx];
                 if let Some(ref n) = prod.precedence {
                     if !self.tokens.contains(n) {
-                        return Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownToken,
-                            sym: Some(Symbol::Term(n.clone()))});
-                    }
-                    if !self.precs.contains_key(n) {
-                        return Err(GrammarValidationError{kind: GrammarValidationErrorKind::NoPrecForToken,
-                            sym: Some(Symbol::Term(n.clone()))});
+                        errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownToken,
+                            sym: Some(Symbol::Term(n.clone())), span: prod.precedence_span});
+                    } else if !self.precs.contains_key(n) {
+                        errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::NoPrecForToken,
+                            sym: Some(Symbol::Term(n.clone())), span: prod.precedence_span});
                     }
                 }
-                for sym in &prod.symbols {
+                let mut labels = HashSet::new();
+                let dup = prod.labels.iter()
+                                     .zip(&prod.symbol_spans)
+                                     .find(|&(l, _)| l.as_ref().map_or(false, |l| !labels.insert(l)));
+                if let Some((_, span)) = dup {
+                    errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::DuplicateLabel,
+                        sym: Some(Symbol::Nonterm(name.clone())), span: Some(*span)});
+                }
+                for (sym, span) in prod.symbols.iter().zip(&prod.symbol_spans) {
                     match *sym {
                         Symbol::Nonterm(ref name) => {
-                            if !self.rules.contains_key(name) {
-                                return Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownRuleRef,
-                                    sym: Some(sym.clone())});
+                            // Applications are only unexpanded if expansion failed, in which case
+                            // the failure has already been reported.
+                            if !self.rules.contains_key(name) && !self.applications.contains_key(name) {
+                                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownRuleRef,
+                                    sym: Some(sym.clone()), span: Some(*span)});
                             }
                         }
                         Symbol::Term(ref name) => {
                             if !self.tokens.contains(name) {
-                                return Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownToken,
-                                    sym: Some(sym.clone())});
+                                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownToken,
+                                    sym: Some(sym.clone()), span: Some(*span)});
+                            }
+                        },
+                        Symbol::Error => ()
+                    }
+                }
+            }
+        }
+        for name in self.rules.keys() {
+            if !self.inline_rules.contains(name) || self.templates.contains_key(name) {
+                continue;
+            }
+            if self.start.as_ref() == Some(name) || self.extra_starts.contains(name) {
+                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::InlineStartRule,
+                    sym: Some(Symbol::Nonterm(name.clone())),
+                    span: self.rule_spans.get(name).cloned()});
+            }
+            if self.inline_reaches(name, name) {
+                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::RecursiveInlineRule,
+                    sym: Some(Symbol::Nonterm(name.clone())),
+                    span: self.rule_spans.get(name).cloned()});
+            }
+            if self.rules[name].iter().any(|&i| self.prods[i].action.is_some()) {
+                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::InlineRuleAction,
+                    sym: Some(Symbol::Nonterm(name.clone())),
+                    span: self.rule_spans.get(name).cloned()});
+            }
+        }
+        for (name, prod_idxs) in &self.rules {
+            if self.templates.contains_key(name) {
+                continue;
+            }
+            let mut prod_names = HashSet::new();
+            for &prod_idx in prod_idxs {
+                let prod = &self.prods[prod_idx];
+                if let Some(ref n) = prod.name {
+                    if !prod_names.insert(n) {
+                        errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::DuplicateProductionName,
+                            sym: Some(Symbol::Nonterm(name.clone())), span: Some(prod.span)});
+                        break;
+                    }
+                }
+            }
+        }
+        for name in self.types.keys() {
+            if !self.rules.contains_key(name) && !self.tokens.contains(name)
+               && !self.templates.contains_key(name) {
+                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownTypeSymbol,
+                    sym: Some(Symbol::Nonterm(name.clone())), span: None});
+            }
+        }
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
+
+    /// Expand each application of a parameterised rule (e.g. `list('a')`) reachable from the
+    /// non-parameterised rules into a rule of its own (named after the application), and then
+    /// remove the parameterised rules (and their productions). If any errors are found, they are
+    /// added to `errs` and nothing is expanded.
+    fn expand_templates(&mut self, errs: &mut Vec<GrammarValidationError>) {
+        if self.templates.is_empty() {
+            return;
+        }
+        let errs_len = errs.len();
+        let mut seen = HashSet::new();
+        for name in self.rules.keys() {
+            for p in self.templates.get(name).map_or(&[][..], |x| x.as_slice()) {
+                if (self.rules.contains_key(p) || self.tokens.contains(p)) && seen.insert(p) {
+                    errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::ParameterShadowsSymbol,
+                        sym: Some(Symbol::Nonterm(p.clone())),
+                        span: self.rule_spans.get(name).cloned()});
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        for prod in &self.prods {
+            // Errors in (possibly nested) arguments are reported at the span of the outermost
+            // application in the production.
+            let mut todo = prod.symbols.iter().zip(prod.symbol_spans.iter().cloned()).collect::<Vec<_>>();
+            while let Some((sym, span)) = todo.pop() {
+                let n = match *sym {
+                    Symbol::Nonterm(ref n) => n,
+                    Symbol::Term(_) | Symbol::Error => continue
+                };
+                if !seen.insert(n) {
+                    continue;
+                }
+                if let Some((template, args)) = self.applications.get(n) {
+                    if !self.rules.contains_key(template) {
+                        errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownRuleRef,
+                            sym: Some(Symbol::Nonterm(template.clone())), span: Some(span)});
+                    } else if self.templates.get(template).map_or(0, |x| x.len()) != args.len() {
+                        errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::WrongNumberOfArguments,
+                            sym: Some(Symbol::Nonterm(template.clone())), span: Some(span)});
+                    }
+                    todo.extend(args.iter().map(|a| (a, span)));
+                } else if self.templates.contains_key(n) {
+                    errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::WrongNumberOfArguments,
+                        sym: Some(sym.clone()), span: Some(span)});
+                }
+            }
+        }
+        if errs.len() == errs_len {
+            for name in self.diverging_templates() {
+                let span = self.rule_spans.get(&name).cloned();
+                errs.push(GrammarValidationError{kind: GrammarValidationErrorKind::NonTerminatingExpansion,
+                    sym: Some(Symbol::Nonterm(name)), span});
+            }
+        }
+        if errs.len() > errs_len {
+            return;
+        }
+
+        // Starting from the non-parameterised rules, instantiate every application we find in a
+        // rule's productions, and then look in the instantiated rule's productions in turn.
+        let mut todo = self.rules.keys()
+                                 .filter(|n| !self.templates.contains_key(*n))
+                                 .cloned()
+                                 .collect::<Vec<_>>();
+        let mut i = 0;
+        while i < todo.len() {
+            for prod_idx in self.rules[&todo[i]].clone() {
+                for sym_idx in 0..self.prods[prod_idx].symbols.len() {
+                    let app_name = match self.prods[prod_idx].symbols[sym_idx] {
+                        Symbol::Nonterm(ref n) if self.applications.contains_key(n)
+                                                  && !self.rules.contains_key(n) => n.clone(),
+                        _ => continue
+                    };
+                    self.instantiate(&app_name);
+                    todo.push(app_name);
+                }
+            }
+            i += 1;
+        }
+
+        // Remove the parameterised rules and their productions, updating the indexes of the
+        // remaining productions.
+        let mut removed = HashSet::new();
+        for name in self.templates.keys() {
+            removed.extend(self.rules[name].iter().cloned());
+            self.rule_spans.remove(name);
+            self.synthetic_rules.remove(name);
+            self.inline_rules.remove(name);
+        }
+        let templates = &self.templates;
+        self.rules.retain(|n, _| !templates.contains_key(n));
+        let mut new_idxs = Vec::with_capacity(self.prods.len());
+        let mut prods = Vec::with_capacity(self.prods.len() - removed.len());
+        for (i, prod) in self.prods.drain(..).enumerate() {
+            if removed.contains(&i) {
+                new_idxs.push(None);
+            } else {
+                new_idxs.push(Some(prods.len()));
+                prods.push(prod);
+            }
+        }
+        self.prods = prods;
+        for prod_idxs in self.rules.values_mut() {
+            for prod_idx in prod_idxs.iter_mut() {
+                *prod_idx = new_idxs[*prod_idx].unwrap();
+            }
+        }
+    }
+
+    /// Return the names of the parameterised rules whose expansion would never terminate, because
+    /// one of their parameters is (perhaps indirectly) passed back to them as part of a larger
+    /// argument (e.g. `f(X): f(list(X));`).
+    fn diverging_templates(&self) -> Vec<String> {
+        // A graph whose nodes are (parameterised rule, parameter index) pairs. An edge from
+        // (f, i) to (g, j) means that f's ith parameter appears in g's jth argument in an
+        // application in f's productions: the edge is "growing" if the argument is anything other
+        // than the parameter itself.
+        let mut edges: HashMap<(&str, usize), Vec<(&str, usize)>> = HashMap::new();
+        let mut growing = Vec::new();
+        for (name, prod_idxs) in &self.rules {
+            let params = match self.templates.get(name) {
+                Some(params) => params,
+                None => continue
+            };
+            for &prod_idx in prod_idxs {
+                let mut todo = self.prods[prod_idx].symbols.iter().collect::<Vec<_>>();
+                while let Some(sym) = todo.pop() {
+                    let (template, args) = match *sym {
+                        Symbol::Nonterm(ref n) => match self.applications.get(n) {
+                            Some((template, args)) => (template, args),
+                            None => continue
+                        },
+                        Symbol::Term(_) | Symbol::Error => continue
+                    };
+                    for (j, arg) in args.iter().enumerate() {
+                        for (i, p) in params.iter().enumerate() {
+                            let grows = match *arg {
+                                Symbol::Nonterm(ref n) if n == p => false,
+                                _ if self.mentions(arg, p) => true,
+                                _ => continue
+                            };
+                            let from = (name.as_str(), i);
+                            let to = (template.as_str(), j);
+                            edges.entry(from).or_insert_with(Vec::new).push(to);
+                            if grows {
+                                growing.push((from, to));
+                            }
+                        }
+                        todo.push(arg);
+                    }
+                }
+            }
+        }
+        // Expansion diverges if there is a cycle containing a growing edge.
+        let mut diverging = Vec::new();
+        for (from, to) in growing {
+            let mut seen = HashSet::new();
+            let mut todo = vec![to];
+            while let Some(n) = todo.pop() {
+                if n == from {
+                    if !diverging.iter().any(|x| x == from.0) {
+                        diverging.push(from.0.to_string());
+                    }
+                    break;
+                }
+                if seen.insert(n) {
+                    if let Some(es) = edges.get(&n) {
+                        todo.extend(es);
+                    }
+                }
+            }
+        }
+        diverging
+    }
+
+    /// Does `sym` refer to the parameter `param`, either directly or in the arguments of an
+    /// application?
+    fn mentions(&self, sym: &Symbol, param: &str) -> bool {
+        match *sym {
+            Symbol::Nonterm(ref n) => {
+                n == param || self.applications.get(n).map_or(false, |app| {
+                    app.1.iter().any(|a| self.mentions(a, param))
+                })
+            },
+            Symbol::Term(_) | Symbol::Error => false
+        }
+    }
+
+    /// Create the rule `app_name` by substituting the arguments of the application `app_name`
+    /// for the parameters of the parameterised rule it applies.
+    fn instantiate(&mut self, app_name: &str) {
+        let (template, args) = self.applications[app_name].clone();
+        let subst = self.templates[&template].iter()
+                                             .cloned()
+                                             .zip(args)
+                                             .collect::<HashMap<_, _>>();
+        for prod_idx in self.rules[&template].clone() {
+            let symbols = self.prods[prod_idx].symbols.clone()
+                                              .iter()
+                                              .map(|s| self.substitute(s, &subst))
+                                              .collect();
+            let prod = &self.prods[prod_idx];
+            let prod = Production{symbols,
+                                  precedence: prod.precedence.clone(),
+                                  action: prod.action.clone(),
+                                  span: prod.span,
+                                  symbol_spans: prod.symbol_spans.clone(),
+                                  precedence_span: prod.precedence_span,
+                                  empty_span: prod.empty_span,
+                                  labels: prod.labels.clone(),
+                                  name: prod.name.clone()};
+            self.add_production(app_name.to_string(), prod);
+        }
+        if let Some(span) = self.rule_spans.get(&template).cloned() {
+            self.rule_spans.insert(app_name.to_string(), span);
+        }
+        if self.synthetic_rules.contains(&template) {
+            self.synthetic_rules.insert(app_name.to_string());
+        }
+        if self.inline_rules.contains(&template) {
+            self.inline_rules.insert(app_name.to_string());
+        }
+        if let Some(t) = self.types.get(&template).cloned() {
+            self.types.insert(app_name.to_string(), t);
+        }
+    }
+
+    /// Return the name of the application of the parameterised rule `template` to `args`.
+    fn instance_name(&self, template: &str, args: &[Symbol]) -> String {
+        if !self.synthetic_rules.contains(template) {
+            return application_name(template, args);
+        }
+        let params = &self.templates[template];
+        RE_SYNTHETIC_NAME_PART.replace_all(template, |caps: &Captures| {
+            match params.iter().position(|p| p == &caps[0]) {
+                Some(i) => symbol_name(&args[i]),
+                None => caps[0].to_string()
+            }
+        }).into_owned()
+    }
+
+    /// Return `sym` with the parameters in `subst` replaced by their arguments.
+    fn substitute(&mut self, sym: &Symbol, subst: &HashMap<String, Symbol>) -> Symbol {
+        if let Symbol::Nonterm(ref n) = *sym {
+            if let Some(arg) = subst.get(n) {
+                return arg.clone();
+            }
+            if let Some((template, args)) = self.applications.get(n).cloned() {
+                let args = args.iter().map(|a| self.substitute(a, subst)).collect::<Vec<_>>();
+                let app_name = self.instance_name(&template, &args);
+                self.applications.entry(app_name.clone()).or_insert((template, args));
+                return Symbol::Nonterm(app_name);
+            }
+        }
+        sym.clone()
+    }
+
+    /// Can the %inline rule `from` reach the rule `to` via a chain of references to %inline rules?
+    fn inline_reaches(&self, from: &str, to: &str) -> bool {
+        let mut seen = HashSet::new();
+        let mut todo = vec![from];
+        while let Some(n) = todo.pop() {
+            for &prod_idx in &self.rules[n] {
+                for sym in &self.prods[prod_idx].symbols {
+                    if let Symbol::Nonterm(ref m) = *sym {
+                        if m == to {
+                            return true;
+                        }
+                        if self.inline_rules.contains(m) && self.rules.contains_key(m)
+                           && seen.insert(m.as_str()) {
+                            todo.push(m);
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Return a copy of this (validated) AST in which every reference to an %inline rule has been
+    /// replaced by that rule's productions, and from which the %inline rules have been removed.
+    /// A production which references an %inline rule is replaced by one production for each
+    /// combination of the %inline rules' productions, in the order they were defined. The
+    /// resulting productions keep their own %prec (if any) or, failing that, take the %prec (if
+    /// any) of the first %inline production to have one: otherwise, as normal, their precedence is
+    /// that of their rightmost terminal.
+    pub(crate) fn inline(&self) -> GrammarAST {
+        let mut ast = self.clone();
+        let mut prod_rules = vec![None; self.prods.len()];
+        for (name, prod_idxs) in &self.rules {
+            for &prod_idx in prod_idxs {
+                prod_rules[prod_idx] = Some(name);
+            }
+        }
+        ast.rules = self.rules.keys()
+                              .filter(|n| !self.inline_rules.contains(*n))
+                              .map(|n| (n.clone(), Vec::new()))
+                              .collect();
+        ast.prods = Vec::with_capacity(self.prods.len());
+        for (prod, name) in self.prods.iter().zip(prod_rules) {
+            let name = name.unwrap();
+            if self.inline_rules.contains(name) {
+                continue;
+            }
+            for p in self.inline_prod(prod) {
+                ast.rules[name].push(ast.prods.len());
+                ast.prods.push(p);
+            }
+        }
+        for name in &self.inline_rules {
+            ast.rule_spans.remove(name);
+        }
+        ast.inline_rules.clear();
+        ast
+    }
+
+    /// Return the productions which result from substituting every %inline rule referenced in
+    /// `prod`. Since %inline rules can't have actions, the symbols substituted into `prod` are
+    /// unlabelled (as is any label on the %inline rule's reference in `prod`). Every resulting
+    /// production has `prod`'s name, so e.g. `E: E binop E #BinOp; %inline binop: '+' | '-';`
+    /// results in two productions named `BinOp`.
+    fn inline_prod(&self, prod: &Production) -> Vec<Production> {
+        let mut prods = vec![Production{symbols: Vec::new(),
+                                        precedence: prod.precedence.clone(),
+                                        action: prod.action.clone(),
+                                        span: prod.span,
+                                        symbol_spans: Vec::new(),
+                                        precedence_span: prod.precedence_span,
+                                        empty_span: prod.empty_span,
+                                        labels: Vec::new(),
+                                        name: prod.name.clone()}];
+        for ((sym, span), label) in prod.symbols.iter().zip(&prod.symbol_spans).zip(&prod.labels) {
+            match *sym {
+                Symbol::Nonterm(ref n) if self.inline_rules.contains(n) => {
+                    let alts = self.rules[n].iter()
+                                            .flat_map(|&i| self.inline_prod(&self.prods[i]))
+                                            .collect::<Vec<_>>();
+                    let mut new_prods = Vec::with_capacity(prods.len() * alts.len());
+                    for p in &prods {
+                        for alt in &alts {
+                            let mut new_prod = p.clone();
+                            new_prod.symbols.extend(alt.symbols.iter().cloned());
+                            new_prod.symbol_spans.extend(alt.symbol_spans.iter().cloned());
+                            new_prod.labels.extend(alt.symbols.iter().map(|_| None));
+                            if new_prod.precedence.is_none() {
+                                new_prod.precedence = alt.precedence.clone();
+                                new_prod.precedence_span = alt.precedence_span;
                             }
+                            new_prods.push(new_prod);
                         }
                     }
+                    prods = new_prods;
+                },
+                _ => {
+                    for p in &mut prods {
+                        p.symbols.push(sym.clone());
+                        p.symbol_spans.push(*span);
+                        p.labels.push(label.clone());
+                    }
                 }
             }
         }
-        Ok(())
+        prods
+    }
+}
+
+/// Return the name used for `sym` when naming synthetic rules and the instances of parameterised
+/// rules. Since terminals are quoted, and since neither quotes nor EBNF operators can appear in
+/// rule names, these names can never clash with those of user-defined rules.
+pub(crate) fn symbol_name(sym: &Symbol) -> String {
+    match *sym {
+        Symbol::Nonterm(ref n) => n.clone(),
+        Symbol::Term(ref n) => format!("'{}'", n.replace('\\', "\\\\").replace('\'', "\\'")),
+        Symbol::Error => ERROR_TERM.to_string()
     }
 }
 
+/// Return the name of the application of the parameterised rule `name` to `args` (e.g.
+/// `list('a', b)`).
+pub(crate) fn application_name(name: &str, args: &[Symbol]) -> String {
+    format!("{}({})", name, args.iter().map(symbol_name).collect::<Vec<_>>().join(", "))
+}
+
 #[cfg(test)]
 mod test {
     use super::{GrammarAST, GrammarValidationError, GrammarValidationErrorKind, Symbol};
     use yacc::{AssocKind, Precedence};
 
+    /// Validate `grm`, returning the first validation error (if any).
+    fn validate(grm: &mut GrammarAST) -> Result<(), GrammarValidationError> {
+        grm.complete_and_validate().map_err(|mut errs| errs.remove(0))
+    }
+
     fn nonterminal(n: &str) -> Symbol {
         Symbol::Nonterm(n.to_string())
     }
@@ -215,7 +831,7 @@ mod test {
     #[test]
     fn test_empty_grammar(){
         let mut grm = GrammarAST::new();
-        match grm.complete_and_validate() {
+        match validate(&mut grm) {
             Err(GrammarValidationError{kind: GrammarValidationErrorKind::NoStartRule, ..}) => (),
             _ => panic!("Validation error")
         }
@@ -225,8 +841,8 @@ mod test {
     fn test_invalid_start_rule(){
         let mut grm = GrammarAST::new();
         grm.start = Some("A".to_string());
-        grm.add_prod("B".to_string(), vec!(), None);
-        match grm.complete_and_validate() {
+        grm.add_prod("B".to_string(), vec!(), None, None);
+        match validate(&mut grm) {
             Err(GrammarValidationError{kind: GrammarValidationErrorKind::InvalidStartRule, ..}) => (),
             _ => panic!("Validation error")
         }
@@ -236,25 +852,25 @@ mod test {
     fn test_valid_start_rule(){
         let mut grm = GrammarAST::new();
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(), None);
-        assert!(grm.complete_and_validate().is_ok());
+        grm.add_prod("A".to_string(), vec!(), None, None);
+        assert!(validate(&mut grm).is_ok());
     }
 
     #[test]
     fn test_valid_nonterminal_ref(){
         let mut grm = GrammarAST::new();
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(nonterminal("B")), None);
-        grm.add_prod("B".to_string(), vec!(), None);
-        assert!(grm.complete_and_validate().is_ok());
+        grm.add_prod("A".to_string(), vec!(nonterminal("B")), None, None);
+        grm.add_prod("B".to_string(), vec!(), None, None);
+        assert!(validate(&mut grm).is_ok());
     }
 
     #[test]
     fn test_invalid_nonterminal_ref(){
         let mut grm = GrammarAST::new();
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(nonterminal("B")), None);
-        match grm.complete_and_validate() {
+        grm.add_prod("A".to_string(), vec!(nonterminal("B")), None, None);
+        match validate(&mut grm) {
             Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownRuleRef, ..}) => (),
             _ => panic!("Validation error")
         }
@@ -265,8 +881,8 @@ mod test {
         let mut grm = GrammarAST::new();
         grm.tokens.insert("b".to_string());
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(terminal("b")), None);
-        assert!(grm.complete_and_validate().is_ok());
+        grm.add_prod("A".to_string(), vec!(terminal("b")), None, None);
+        assert!(validate(&mut grm).is_ok());
     }
 
     #[test]
@@ -277,16 +893,16 @@ mod test {
         let mut grm = GrammarAST::new();
         grm.tokens.insert("b".to_string());
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(nonterminal("b")), None);
-        assert!(grm.complete_and_validate().is_ok());
+        grm.add_prod("A".to_string(), vec!(nonterminal("b")), None, None);
+        assert!(validate(&mut grm).is_ok());
     }
 
     #[test]
     fn test_invalid_terminal_ref(){
         let mut grm = GrammarAST::new();
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(terminal("b")), None);
-        match grm.complete_and_validate() {
+        grm.add_prod("A".to_string(), vec!(terminal("b")), None, None);
+        match validate(&mut grm) {
             Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownToken, ..}) => (),
             _ => panic!("Validation error")
         }
@@ -296,8 +912,8 @@ mod test {
     fn test_invalid_nonterminal_forgotten_token(){
         let mut grm = GrammarAST::new();
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(nonterminal("b"), terminal("b")), None);
-        match grm.complete_and_validate() {
+        grm.add_prod("A".to_string(), vec!(nonterminal("b"), terminal("b")), None, None);
+        match validate(&mut grm) {
             Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownRuleRef, ..}) => (),
             _ => panic!("Validation error")
         }
@@ -309,23 +925,105 @@ mod test {
         grm.precs.insert("b".to_string(), Precedence{level: 1, kind: AssocKind::Left});
         grm.start = Some("A".to_string());
         grm.tokens.insert("b".to_string());
-        grm.add_prod("A".to_string(), vec!(terminal("b")), Some("b".to_string()));
-        assert!(grm.complete_and_validate().is_ok());
+        grm.add_prod("A".to_string(), vec!(terminal("b")), Some("b".to_string()), None);
+        assert!(validate(&mut grm).is_ok());
     }
 
     #[test]
     fn test_invalid_precedence_override(){
         let mut grm = GrammarAST::new();
         grm.start = Some("A".to_string());
-        grm.add_prod("A".to_string(), vec!(terminal("b")), Some("b".to_string()));
-        match grm.complete_and_validate() {
+        grm.add_prod("A".to_string(), vec!(terminal("b")), Some("b".to_string()), None);
+        match validate(&mut grm) {
             Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownToken, ..}) => (),
             _ => panic!("Validation error")
         }
         grm.tokens.insert("b".to_string());
-        match grm.complete_and_validate() {
+        match validate(&mut grm) {
             Err(GrammarValidationError{kind: GrammarValidationErrorKind::NoPrecForToken, ..}) => (),
             _ => panic!("Validation error")
         }
     }
+
+    #[test]
+    fn test_types(){
+        let mut grm = GrammarAST::new();
+        grm.start = Some("A".to_string());
+        grm.tokens.insert("b".to_string());
+        grm.add_prod("A".to_string(), vec!(terminal("b")), None, None);
+        grm.types.insert("A".to_string(), "u32".to_string());
+        grm.types.insert("b".to_string(), "u8".to_string());
+        assert!(validate(&mut grm).is_ok());
+        grm.types.insert("C".to_string(), "u8".to_string());
+        match validate(&mut grm) {
+            Err(GrammarValidationError{kind: GrammarValidationErrorKind::UnknownTypeSymbol,
+                                       sym: Some(Symbol::Nonterm(ref n)), ..}) if n == "C" => (),
+            _ => panic!("Validation error")
+        }
+    }
+
+    #[test]
+    fn test_parameterised_rules(){
+        let mut grm = GrammarAST::new();
+        grm.start = Some("A".to_string());
+        grm.tokens.insert("a".to_string());
+        grm.templates.insert("f".to_string(), vec!["X".to_string()]);
+        grm.add_prod("f".to_string(), vec!(nonterminal("X")), None, None);
+        grm.add_prod("A".to_string(), vec!(nonterminal("f('a')")), None, None);
+        grm.add_prod("f".to_string(), vec!(terminal("a"), nonterminal("X")), None, None);
+        grm.applications.insert("f('a')".to_string(), ("f".to_string(), vec!(terminal("a"))));
+        assert!(validate(&mut grm).is_ok());
+        assert_eq!(grm.rules.keys().collect::<Vec<_>>(), vec!["A", "f('a')"]);
+        assert_eq!(grm.rules["A"], vec![0]);
+        assert_eq!(grm.rules["f('a')"], vec![1, 2]);
+        assert_eq!(grm.prods.len(), 3);
+        assert_eq!(grm.prods[1].symbols, vec![terminal("a")]);
+        assert_eq!(grm.prods[2].symbols, vec![terminal("a"), terminal("a")]);
+
+        let mut grm = GrammarAST::new();
+        grm.start = Some("A".to_string());
+        grm.templates.insert("f".to_string(), vec!["X".to_string()]);
+        grm.add_prod("A".to_string(), vec!(nonterminal("f(A)")), None, None);
+        grm.add_prod("f".to_string(), vec!(nonterminal("X"), nonterminal("f(f(X))")), None, None);
+        grm.applications.insert("f(A)".to_string(), ("f".to_string(), vec!(nonterminal("A"))));
+        grm.applications.insert("f(X)".to_string(), ("f".to_string(), vec!(nonterminal("X"))));
+        grm.applications.insert("f(f(X))".to_string(), ("f".to_string(), vec!(nonterminal("f(X)"))));
+        match validate(&mut grm) {
+            Err(GrammarValidationError{kind: GrammarValidationErrorKind::NonTerminatingExpansion,
+                                       sym: Some(Symbol::Nonterm(ref n)), ..}) if n == "f" => (),
+            _ => panic!("Validation error")
+        }
+    }
+
+    #[test]
+    fn test_multiple_errors(){
+        let mut grm = GrammarAST::new();
+        grm.start = Some("X".to_string());
+        grm.add_prod("A".to_string(), vec!(nonterminal("B"), terminal("c")), Some("d".to_string()),
+                     None);
+        grm.types.insert("E".to_string(), "u8".to_string());
+        match grm.complete_and_validate() {
+            Err(ref errs) => {
+                let kinds = errs.iter().map(|e| format!("{:?}", e.kind)).collect::<Vec<_>>();
+                assert_eq!(kinds, vec!["InvalidStartRule", "UnknownToken", "UnknownRuleRef",
+                                       "UnknownToken", "UnknownTypeSymbol"]);
+                assert_eq!(errs[1].sym, Some(terminal("d")));
+                assert_eq!(errs[3].sym, Some(terminal("c")));
+            },
+            _ => panic!("Validation error")
+        }
+    }
+
+    #[test]
+    fn test_no_prec_for_token(){
+        let mut grm = GrammarAST::new();
+        grm.start = Some("A".to_string());
+        grm.tokens.insert("b".to_string());
+        grm.add_prod("A".to_string(), vec!(terminal("b")), Some("b".to_string()), None);
+        match validate(&mut grm) {
+            Err(GrammarValidationError{kind: GrammarValidationErrorKind::NoPrecForToken,
+                                       sym: Some(Symbol::Term(ref n)), ..}) if n == "b" => (),
+            _ => panic!("Validation error")
+        }
+    }
 }
@@ -0,0 +1,371 @@
+// Copyright (c) 2018 King's College London
+// created by the Software Development Team <http://soft-dev.org/>
+//
+// The Universal Permissive License (UPL), Version 1.0
+//
+// Subject to the condition set forth below, permission is hereby granted to any person obtaining a
+// copy of this software, associated documentation and/or data (collectively the "Software"), free
+// of charge and under any and all copyright rights in the Software, and any and all patent rights
+// owned or freely licensable by each licensor hereunder covering either (i) the unmodified
+// Software as contributed to or provided by such licensor, or (ii) the Larger Works (as defined
+// below), to deal in both
+//
+// (a) the Software, and
+// (b) any piece of software and/or hardware listed in the lrgrwrks.txt file
+// if one is included with the Software (each a "Larger Work" to which the Software is contributed
+// by such licensors),
+//
+// without restriction, including without limitation the rights to copy, create derivative works
+// of, display, perform, and distribute the Software and make, use, sell, offer for sale, import,
+// export, have made, and have sold the Software and the Larger Work(s), and to sublicense the
+// foregoing rights on either these or other terms.
+//
+// This license is subject to the following condition: The above copyright notice and either this
+// complete permission notice or at a minimum a reference to the UPL must be included in all copies
+// or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING
+// BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+// NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+// DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+use {Grammar, PIdx, StIdx, Symbol, TIdx};
+use yacc::{AssocKind, YaccGrammar};
+use yacc::lookaheads::Lookaheads;
+use yacc::stategraph::StateGraph;
+
+/// A conflict in a state graph: in state `st_idx`, when the next terminal is `t_idx`, there is
+/// more than one possible action.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Conflict {
+    /// Shifting `t_idx` conflicts with reducing production `p_idx`.
+    ShiftReduce{st_idx: StIdx, t_idx: TIdx, p_idx: PIdx},
+    /// Reducing production `p_idx1` conflicts with reducing production `p_idx2`. `p_idx1` is
+    /// always the production which appears earlier in the grammar.
+    ReduceReduce{st_idx: StIdx, t_idx: TIdx, p_idx1: PIdx, p_idx2: PIdx}
+}
+
+impl Conflict {
+    /// Return the action that Yacc takes for this conflict if it can't be resolved by
+    /// precedence: shift for shift/reduce conflicts; and reducing the production that appears
+    /// earlier in the grammar for reduce/reduce conflicts.
+    pub fn default_resolution(&self) -> Resolution {
+        match *self {
+            Conflict::ShiftReduce{..} => Resolution::Shift,
+            Conflict::ReduceReduce{p_idx1, ..} => Resolution::Reduce(p_idx1)
+        }
+    }
+}
+
+/// How a conflict was resolved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Resolution {
+    /// Shift the terminal.
+    Shift,
+    /// Reduce the production.
+    Reduce(PIdx),
+    /// Neither shift nor reduce: the terminal is a syntax error in this state (e.g. `a < b < c`
+    /// when `<` is declared `%nonassoc`).
+    Error
+}
+
+/// The result of resolving a set of conflicts: those that were resolved (and how) and those that
+/// remain.
+#[derive(Debug)]
+pub struct ConflictReport {
+    resolved: Vec<(Conflict, Resolution)>,
+    unresolved: Vec<Conflict>
+}
+
+impl ConflictReport {
+    /// Return the conflicts which were resolved by precedence and how each was resolved.
+    pub fn resolved(&self) -> &[(Conflict, Resolution)] {
+        &self.resolved
+    }
+
+    /// Return the conflicts which could not be resolved by precedence. Yacc resolves these using
+    /// `Conflict::default_resolution` but reports them to the user.
+    pub fn unresolved(&self) -> &[Conflict] {
+        &self.unresolved
+    }
+
+    /// How many shift/reduce conflicts could not be resolved?
+    pub fn sr_len(&self) -> usize {
+        self.unresolved.iter()
+                       .filter(|x| matches!(**x, Conflict::ShiftReduce{..}))
+                       .count()
+    }
+
+    /// How many reduce/reduce conflicts could not be resolved?
+    pub fn rr_len(&self) -> usize {
+        self.unresolved.len() - self.sr_len()
+    }
+
+    /// Do the number of unresolved shift/reduce and reduce/reduce conflicts match those declared
+    /// in `grm` by `%expect` and `%expect-rr` respectively? If either declaration is missing, no
+    /// conflicts of that kind are expected.
+    pub fn matches_expected(&self, grm: &YaccGrammar) -> bool {
+        self.sr_len() == grm.expect().unwrap_or(0) && self.rr_len() == grm.expectrr().unwrap_or(0)
+    }
+}
+
+/// Find all the conflicts in the state graph `sg` of `grm` with lookaheads `las`. Following Yacc,
+/// if several productions can be reduced on the same terminal, the production earliest in the
+/// grammar has a reduce/reduce conflict with each of the others, and only it is considered for a
+/// shift/reduce conflict. Since productions desugared from EBNF, parameterised, or %inline rules
+/// aren't necessarily numbered in source order, "earliest" is determined by each production's
+/// span (productions inserted by cfgrammar, which have no span, come first).
+pub fn find_conflicts(grm: &YaccGrammar, sg: &StateGraph, las: &Lookaheads) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for st_idx in sg.iter_stidxs() {
+        let reductions = las.reductions(st_idx);
+        for i in 0..grm.terms_len() {
+            let t_idx = TIdx::from(i);
+            let mut p_idxs = reductions.iter()
+                                       .filter(|&(_, la)| la[usize::from(t_idx)])
+                                       .map(|(p_idx, _)| *p_idx)
+                                       .collect::<Vec<_>>();
+            if p_idxs.is_empty() {
+                continue;
+            }
+            p_idxs.sort_by_key(|&p_idx| (grm.prod_span(p_idx).map(|s| s.start()), p_idx));
+            for &p_idx2 in &p_idxs[1..] {
+                conflicts.push(Conflict::ReduceReduce{st_idx, t_idx, p_idx1: p_idxs[0], p_idx2});
+            }
+            if sg.edge(st_idx, Symbol::Term(t_idx)).is_some() {
+                conflicts.push(Conflict::ShiftReduce{st_idx, t_idx, p_idx: p_idxs[0]});
+            }
+        }
+    }
+    conflicts
+}
+
+/// Resolve `conflicts` in the way that Yacc does. A shift/reduce conflict is resolved if both the
+/// production and the terminal have a precedence: if one has a higher precedence level than the
+/// other it wins; if they have the same level then `%left` reduces, `%right` shifts, and
+/// `%nonassoc` makes the terminal an error. Reduce/reduce conflicts are never resolved.
+pub fn resolve_conflicts(grm: &YaccGrammar, conflicts: &[Conflict]) -> ConflictReport {
+    let mut resolved = Vec::new();
+    let mut unresolved = Vec::new();
+    for &conflict in conflicts {
+        let (t_idx, p_idx) = match conflict {
+            Conflict::ShiftReduce{t_idx, p_idx, ..} => (t_idx, p_idx),
+            Conflict::ReduceReduce{..} => {
+                unresolved.push(conflict);
+                continue;
+            }
+        };
+        match (grm.prod_precedence(p_idx), grm.term_precedence(t_idx)) {
+            (Some(p_prec), Some(t_prec)) => {
+                let res = if p_prec.level > t_prec.level {
+                    Resolution::Reduce(p_idx)
+                } else if p_prec.level < t_prec.level {
+                    Resolution::Shift
+                } else {
+                    match t_prec.kind {
+                        AssocKind::Left     => Resolution::Reduce(p_idx),
+                        AssocKind::Right    => Resolution::Shift,
+                        AssocKind::Nonassoc => Resolution::Error
+                    }
+                };
+                resolved.push((conflict, res));
+            },
+            _ => unresolved.push(conflict)
+        }
+    }
+    ConflictReport{resolved, unresolved}
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Conflict, find_conflicts, resolve_conflicts, Resolution};
+    use {PIdx, Symbol};
+    use yacc::{lalr1, yacc_grm, YaccGrammar, YaccKind};
+    use yacc::stategraph::StateGraph;
+
+    fn prod(grm: &YaccGrammar, n: &str, i: usize) -> PIdx {
+        grm.nonterm_to_prods(grm.nonterm_idx(n).unwrap())[i]
+    }
+
+    fn conflicts(grm: &YaccGrammar) -> (StateGraph, Vec<Conflict>) {
+        let sg = StateGraph::new(grm);
+        let las = lalr1(grm, &sg);
+        let conflicts = find_conflicts(grm, &sg, &las);
+        (sg, conflicts)
+    }
+
+    #[test]
+    fn test_no_conflicts() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start E
+            %%
+            E: E '+' T | T;
+            T: T '*' F | F;
+            F: '(' E ')' | 'id';
+          ").unwrap();
+        let (_, cs) = conflicts(&grm);
+        assert!(cs.is_empty());
+        let report = resolve_conflicts(&grm, &cs);
+        assert!(report.resolved().is_empty() && report.unresolved().is_empty());
+    }
+
+    #[test]
+    fn test_precedence_resolution() {
+        let src = "
+            %start E
+            %left '+' '-'
+            %left '*'
+            %right '^'
+            %nonassoc '<'
+            %%
+            E: E '+' E | E '-' E | E '*' E | E '^' E | E '<' E | 'id';
+          ";
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        let (_, cs) = conflicts(&grm);
+        // Each of the 5 binary operator states conflicts with each of the 5 operators.
+        assert_eq!(cs.len(), 25);
+        let report = resolve_conflicts(&grm, &cs);
+        assert!(report.unresolved().is_empty());
+        assert_eq!(report.sr_len(), 0);
+        assert_eq!(report.rr_len(), 0);
+
+        // Find how the conflict of reducing production `p` on terminal `t` was resolved.
+        let res = |p: usize, t: &str| {
+            let p_idx = prod(&grm, "E", p);
+            let t_idx = grm.term_idx(t).unwrap();
+            report.resolved()
+                  .iter()
+                  .find(|&&(c, _)| match c {
+                      Conflict::ShiftReduce{t_idx: c_t_idx, p_idx: c_p_idx, ..} =>
+                          c_t_idx == t_idx && c_p_idx == p_idx,
+                      _ => false
+                  })
+                  .unwrap()
+                  .1
+        };
+        // E '+' E . '+' => reduce (%left)
+        assert_eq!(res(0, "+"), Resolution::Reduce(prod(&grm, "E", 0)));
+        // E '+' E . '-' => reduce (same level, %left)
+        assert_eq!(res(0, "-"), Resolution::Reduce(prod(&grm, "E", 0)));
+        // E '+' E . '*' => shift ('*' has a higher precedence)
+        assert_eq!(res(0, "*"), Resolution::Shift);
+        // E '*' E . '+' => reduce (E '*' E has a higher precedence)
+        assert_eq!(res(2, "+"), Resolution::Reduce(prod(&grm, "E", 2)));
+        // E '^' E . '^' => shift (%right)
+        assert_eq!(res(3, "^"), Resolution::Shift);
+        // E '<' E . '<' => error (%nonassoc)
+        assert_eq!(res(4, "<"), Resolution::Error);
+    }
+
+    #[test]
+    fn test_inline_precedence() {
+        // Precedences only work through a helper rule for operators if it's %inline.
+        let src = "
+            %start E
+            %left '+'
+            %left '*'
+            %%
+            E: E binop E | 'id';
+            binop: '+' | '*';
+          ";
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        let (_, cs) = conflicts(&grm);
+        assert!(!resolve_conflicts(&grm, &cs).unresolved().is_empty());
+
+        let grm = yacc_grm(YaccKind::Original, &src.replace("binop:", "%inline binop:")).unwrap();
+        let (_, cs) = conflicts(&grm);
+        assert_eq!(cs.len(), 4);
+        assert!(resolve_conflicts(&grm, &cs).unresolved().is_empty());
+    }
+
+    #[test]
+    fn test_unresolved_shift_reduce() {
+        // The classic dangling else.
+        let grm = yacc_grm(YaccKind::Original, "
+            %start S
+            %%
+            S: 'if' 'e' 'then' S | 'if' 'e' 'then' S 'else' S | 'x';
+          ").unwrap();
+        let (sg, cs) = conflicts(&grm);
+        assert_eq!(cs.len(), 1);
+        let report = resolve_conflicts(&grm, &cs);
+        assert!(report.resolved().is_empty());
+        assert_eq!(report.sr_len(), 1);
+        assert_eq!(report.rr_len(), 0);
+        match report.unresolved()[0] {
+            Conflict::ShiftReduce{st_idx, t_idx, p_idx} => {
+                assert_eq!(t_idx, grm.term_idx("else").unwrap());
+                assert_eq!(p_idx, prod(&grm, "S", 0));
+                assert!(sg.edge(st_idx, Symbol::Term(t_idx)).is_some());
+            },
+            _ => panic!()
+        }
+        assert_eq!(report.unresolved()[0].default_resolution(), Resolution::Shift);
+        assert!(!report.matches_expected(&grm));
+
+        let grm = yacc_grm(YaccKind::Original, "
+            %start S
+            %expect 1
+            %%
+            S: 'if' 'e' 'then' S | 'if' 'e' 'then' S 'else' S | 'x';
+          ").unwrap();
+        let (_, cs) = conflicts(&grm);
+        assert!(resolve_conflicts(&grm, &cs).matches_expected(&grm));
+    }
+
+    #[test]
+    fn test_reduce_reduce() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start S
+            %left 'e'
+            %%
+            S: 'a' E 'c' | 'a' F 'd' | 'b' F 'c' | 'b' E 'd';
+            E: 'e';
+            F: 'e';
+          ").unwrap();
+        let (_, cs) = conflicts(&grm);
+        // LALR(1) merges two states, leading to conflicts on both 'c' and 'd'. Precedence never
+        // resolves reduce/reduce conflicts.
+        assert_eq!(cs.len(), 2);
+        let report = resolve_conflicts(&grm, &cs);
+        assert!(report.resolved().is_empty());
+        assert_eq!(report.sr_len(), 0);
+        assert_eq!(report.rr_len(), 2);
+        for c in report.unresolved() {
+            match *c {
+                Conflict::ReduceReduce{p_idx1, p_idx2, ..} => {
+                    assert_eq!(p_idx1, prod(&grm, "E", 0));
+                    assert_eq!(p_idx2, prod(&grm, "F", 0));
+                },
+                _ => panic!()
+            }
+            assert_eq!(c.default_resolution(), Resolution::Reduce(prod(&grm, "E", 0)));
+        }
+        assert!(!report.matches_expected(&grm));
+    }
+
+    #[test]
+    fn test_reduce_reduce_source_order() {
+        // The instance of `X?` is created when `f` is expanded, so its productions are numbered
+        // after `B`'s, even though they're earlier in the source.
+        let grm = yacc_grm(YaccKind::Original, "
+            %start S
+            %%
+            S: f('a') 'x' | B 'x';
+            f(X): X?;
+            B: 'a';
+          ").unwrap();
+        let opt = prod(&grm, "'a'?", 0);
+        assert!(opt > prod(&grm, "B", 0));
+        let (_, cs) = conflicts(&grm);
+        assert_eq!(cs.len(), 1);
+        match cs[0] {
+            Conflict::ReduceReduce{p_idx1, p_idx2, ..} => {
+                assert_eq!(p_idx1, opt);
+                assert_eq!(p_idx2, prod(&grm, "B", 0));
+            },
+            _ => panic!()
+        }
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright (c) 2018 King's College London
+// created by the Software Development Team <http://soft-dev.org/>
+//
+// The Universal Permissive License (UPL), Version 1.0
+//
+// Subject to the condition set forth below, permission is hereby granted to any person obtaining a
+// copy of this software, associated documentation and/or data (collectively the "Software"), free
+// of charge and under any and all copyright rights in the Software, and any and all patent rights
+// owned or freely licensable by each licensor hereunder covering either (i) the unmodified
+// Software as contributed to or provided by such licensor, or (ii) the Larger Works (as defined
+// below), to deal in both
+//
+// (a) the Software, and
+// (b) any piece of software and/or hardware listed in the lrgrwrks.txt file
+// if one is included with the Software (each a "Larger Work" to which the Software is contributed
+// by such licensors),
+//
+// without restriction, including without limitation the rights to copy, create derivative works
+// of, display, perform, and distribute the Software and make, use, sell, offer for sale, import,
+// export, have made, and have sold the Software and the Larger Work(s), and to sublicense the
+// foregoing rights on either these or other terms.
+//
+// This license is subject to the following condition: The above copyright notice and either this
+// complete permission notice or at a minimum a reference to the UPL must be included in all copies
+// or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING
+// BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+// NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+// DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+use {Grammar, NTIdx, Symbol, TIdx};
+use yacc::YaccGrammar;
+
+/// `Firsts` stores all the first sets for a given grammar. For example, given this code and
+/// grammar:
+///
+/// ```text
+///   let grm = yacc_grm(YaccKind::Original, "
+///     S: A 'b';
+///     A: 'a'
+///      | ;").unwrap();
+///   let firsts = grm.firsts();
+/// ```
+///
+/// then the following assertions (and only the following assertions) about the firsts set are
+/// correct:
+///
+/// ```text
+///   assert!(firsts.is_set(grm.nonterm_idx("S").unwrap(), grm.term_idx("a").unwrap()));
+///   assert!(firsts.is_set(grm.nonterm_idx("S").unwrap(), grm.term_idx("b").unwrap()));
+///   assert!(firsts.is_set(grm.nonterm_idx("A").unwrap(), grm.term_idx("a").unwrap()));
+///   assert!(firsts.is_epsilon_set(grm.nonterm_idx("A").unwrap()));
+/// ```
+#[derive(Debug)]
+pub struct Firsts {
+    /// A mapping from `NTIdx` -> `TIdx` -> `bool` recording whether each terminal is in each
+    /// nonterminal's firsts set.
+    firsts: Vec<Vec<bool>>,
+    /// A mapping from `NTIdx` -> `bool` recording whether each nonterminal can derive the empty
+    /// string (i.e. a copy of `YaccGrammar::nullables`).
+    epsilons: Vec<bool>
+}
+
+impl Firsts {
+    /// Generates and returns the firsts set for the given grammar.
+    pub fn new(grm: &YaccGrammar) -> Firsts {
+        let mut firsts = Firsts {
+            firsts  : vec![vec![false; grm.terms_len() as usize]; grm.nonterms_len() as usize],
+            epsilons: grm.nullables().to_vec()
+        };
+
+        // Loop looking for changes to the firsts set, until we reach a fixed point. In essence,
+        // we look at each rule E, and see if any of the nonterminals at the start of its
+        // productions have new elements in since we last looked. If they do, we'll have to do
+        // another round.
+        loop {
+            let mut changed = false;
+            for nt_idx in grm.iter_nonterm_idxs() {
+                for &p_idx in grm.nonterm_to_prods(nt_idx) {
+                    for sym in grm.prod(p_idx) {
+                        match *sym {
+                            Symbol::Term(t_idx) => {
+                                // If the production begins with a terminal, add it to the firsts
+                                // set and move on to the next production.
+                                if firsts.set(nt_idx, t_idx) {
+                                    changed = true;
+                                }
+                                break;
+                            },
+                            Symbol::Nonterm(o_nt_idx) => {
+                                // If the production begins with a nonterminal, then we need to
+                                // add that nonterminal's firsts to our own.
+                                for j in 0..grm.terms_len() as usize {
+                                    if firsts.firsts[usize::from(o_nt_idx)][j]
+                                       && firsts.set(nt_idx, TIdx::from(j)) {
+                                        changed = true;
+                                    }
+                                }
+                                // If the nonterminal can't derive epsilon, we're done with this
+                                // production; otherwise we carry on with the next symbol.
+                                if !grm.nullable(o_nt_idx) {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if !changed {
+                return firsts;
+            }
+        }
+    }
+
+    /// Return all the firsts for nonterminal `nt_idx` as a mapping from `TIdx` -> `bool`.
+    pub fn firsts(&self, nt_idx: NTIdx) -> &[bool] {
+        &self.firsts[usize::from(nt_idx)]
+    }
+
+    /// Returns true if the terminal `t_idx` is in the firsts set for nonterminal `nt_idx`.
+    pub fn is_set(&self, nt_idx: NTIdx, t_idx: TIdx) -> bool {
+        self.firsts[usize::from(nt_idx)][usize::from(t_idx)]
+    }
+
+    /// Returns true if the nonterminal `nt_idx` has epsilon in its firsts set.
+    pub fn is_epsilon_set(&self, nt_idx: NTIdx) -> bool {
+        self.epsilons[usize::from(nt_idx)]
+    }
+
+    /// Ensures that the firsts bit for terminal `t_idx` nonterminal `nt_idx` is set. Returns false
+    /// if it was already set, or true otherwise.
+    fn set(&mut self, nt_idx: NTIdx, t_idx: TIdx) -> bool {
+        let nt = &mut self.firsts[usize::from(nt_idx)];
+        if nt[usize::from(t_idx)] {
+            false
+        } else {
+            nt[usize::from(t_idx)] = true;
+            true
+        }
+    }
+
+    /// Return the firsts of the sequence of symbols `syms` (typically the suffix of a production)
+    /// as a mapping from `TIdx` -> `bool`, and whether the sequence as a whole can derive the
+    /// empty string (which is trivially true if `syms` is empty).
+    pub fn seq_firsts(&self, syms: &[Symbol]) -> (Vec<bool>, bool) {
+        // Every grammar has at least a start rule, so self.firsts[0] always exists.
+        let mut seq = vec![false; self.firsts[0].len()];
+        for sym in syms {
+            match *sym {
+                Symbol::Term(t_idx) => {
+                    seq[usize::from(t_idx)] = true;
+                    return (seq, false);
+                },
+                Symbol::Nonterm(nt_idx) => {
+                    for (i, b) in self.firsts[usize::from(nt_idx)].iter().enumerate() {
+                        if *b {
+                            seq[i] = true;
+                        }
+                    }
+                    if !self.epsilons[usize::from(nt_idx)] {
+                        return (seq, false);
+                    }
+                }
+            }
+        }
+        (seq, true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Firsts;
+    use {Grammar, Symbol, TIdx};
+    use yacc::{yacc_grm, YaccGrammar, YaccKind};
+
+    fn has(grm: &YaccGrammar, firsts: &Firsts, rn: &str, should_be: Vec<&str>) {
+        let nt_i = grm.nonterm_idx(rn).unwrap();
+        for i in 0..grm.terms_len() as usize {
+            let n = match grm.term_name(TIdx::from(i)) {
+                Some(n) => n,
+                None if Some(TIdx::from(i)) == grm.error_term_idx() => "error",
+                None => continue // EOF
+            };
+            match should_be.iter().position(|x| x == &n) {
+                Some(_) => {
+                    if !firsts.is_set(nt_i, TIdx::from(i)) {
+                        panic!("{} is not set in {}", n, rn);
+                    }
+                }
+                None    => {
+                    if firsts.is_set(nt_i, TIdx::from(i)) {
+                        panic!("{} is incorrectly set in {}", n, rn);
+                    }
+                }
+            }
+        }
+        if should_be.iter().position(|x| x == &"").is_some() {
+            assert!(firsts.is_epsilon_set(nt_i));
+        }
+    }
+
+    #[test]
+    fn test_first() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start C
+          %token c d
+          %%
+          C: 'c';
+          D: 'a' | 'b';
+          E: D | C;
+          F: E;
+          ").unwrap();
+        let firsts = grm.firsts();
+        has(&grm, &firsts, "^", vec!["c"]);
+        has(&grm, &firsts, "D", vec!["a", "b"]);
+        has(&grm, &firsts, "E", vec!["a", "b", "c"]);
+        has(&grm, &firsts, "F", vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_first_no_subsequent_nonterminals() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start C
+          %token c d
+          %%
+          C: 'c';
+          D: 'a';
+          E: D 'b';
+          ").unwrap();
+        let firsts = grm.firsts();
+        has(&grm, &firsts, "E", vec!["a"]);
+    }
+
+    #[test]
+    fn test_first_epsilon() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start A
+          %token a b c
+          %%
+          A: B 'a';
+          B: 'b' | ;
+          C: B 'c' B;
+          D: B C;
+          ").unwrap();
+        let firsts = grm.firsts();
+        has(&grm, &firsts, "A", vec!["b", "a"]);
+        has(&grm, &firsts, "C", vec!["b", "c"]);
+        has(&grm, &firsts, "D", vec!["b", "c"]);
+        assert!(!firsts.is_epsilon_set(grm.nonterm_idx("A").unwrap()));
+        assert!(firsts.is_epsilon_set(grm.nonterm_idx("B").unwrap()));
+    }
+
+    #[test]
+    fn test_last_epsilon() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start A
+          %token b c
+          %%
+          A: B C;
+          B: 'b' | ;
+          C: B 'c' B;
+          ").unwrap();
+        let firsts = grm.firsts();
+        has(&grm, &firsts, "A", vec!["b", "c"]);
+        has(&grm, &firsts, "B", vec!["b", ""]);
+        has(&grm, &firsts, "C", vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_first_no_multiples() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start A
+          %token b c
+          %%
+          A: B 'b';
+          B: 'b' | ;
+          ").unwrap();
+        let firsts = grm.firsts();
+        has(&grm, &firsts, "A", vec!["b"]);
+    }
+
+    #[test]
+    fn test_first_recursive_epsilon() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start A
+          %%
+          A: A B | ;
+          B: 'b' | C;
+          C: ;
+          ").unwrap();
+        let firsts = grm.firsts();
+        has(&grm, &firsts, "A", vec!["b", ""]);
+        has(&grm, &firsts, "B", vec!["b", ""]);
+        has(&grm, &firsts, "C", vec![""]);
+    }
+
+    #[test]
+    fn test_first_error() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start A
+          %%
+          A: B ';' | 'a';
+          B: error | ;
+          ").unwrap();
+        let firsts = grm.firsts();
+        has(&grm, &firsts, "A", vec!["error", ";", "a"]);
+        has(&grm, &firsts, "B", vec!["error", ""]);
+    }
+
+    #[test]
+    fn test_seq_firsts() {
+        let grm = yacc_grm(YaccKind::Original, "
+          %start A
+          %%
+          A: B C 'c';
+          B: 'b' | ;
+          C: 'x' | B;
+          ").unwrap();
+        let firsts = grm.firsts();
+        let b_idx = grm.term_idx("b").unwrap();
+        let c_idx = grm.term_idx("c").unwrap();
+        let x_idx = grm.term_idx("x").unwrap();
+        let a_prod = grm.prod(grm.nonterm_to_prods(grm.nonterm_idx("A").unwrap())[0]);
+
+        let (seq, eps) = firsts.seq_firsts(&a_prod[1..]);
+        assert!(!eps);
+        assert!(seq[usize::from(b_idx)] && seq[usize::from(c_idx)] && seq[usize::from(x_idx)]);
+
+        let (seq, eps) = firsts.seq_firsts(&a_prod[..2]);
+        assert!(eps);
+        assert!(seq[usize::from(b_idx)] && !seq[usize::from(c_idx)] && seq[usize::from(x_idx)]);
+
+        let (seq, eps) = firsts.seq_firsts(&[Symbol::Term(c_idx), Symbol::Term(x_idx)]);
+        assert!(!eps);
+        assert_eq!(seq.iter().filter(|x| **x).count(), 1);
+        assert!(seq[usize::from(c_idx)]);
+
+        let (seq, eps) = firsts.seq_firsts(&[]);
+        assert!(eps);
+        assert!(seq.iter().all(|x| !*x));
+    }
+}
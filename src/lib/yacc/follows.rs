@@ -0,0 +1,188 @@
+// Copyright (c) 2018 King's College London
+// created by the Software Development Team <http://soft-dev.org/>
+//
+// The Universal Permissive License (UPL), Version 1.0
+//
+// Subject to the condition set forth below, permission is hereby granted to any person obtaining a
+// copy of this software, associated documentation and/or data (collectively the "Software"), free
+// of charge and under any and all copyright rights in the Software, and any and all patent rights
+// owned or freely licensable by each licensor hereunder covering either (i) the unmodified
+// Software as contributed to or provided by such licensor, or (ii) the Larger Works (as defined
+// below), to deal in both
+//
+// (a) the Software, and
+// (b) any piece of software and/or hardware listed in the lrgrwrks.txt file
+// if one is included with the Software (each a "Larger Work" to which the Software is contributed
+// by such licensors),
+//
+// without restriction, including without limitation the rights to copy, create derivative works
+// of, display, perform, and distribute the Software and make, use, sell, offer for sale, import,
+// export, have made, and have sold the Software and the Larger Work(s), and to sublicense the
+// foregoing rights on either these or other terms.
+//
+// This license is subject to the following condition: The above copyright notice and either this
+// complete permission notice or at a minimum a reference to the UPL must be included in all copies
+// or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING
+// BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+// NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+// DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+use {Grammar, NTIdx, Symbol, TIdx};
+use yacc::YaccGrammar;
+
+/// `Follows` stores all the follow sets for a given grammar. For example, given this code and
+/// grammar:
+///
+/// ```text
+///   let grm = yacc_grm(YaccKind::Original, "
+///     S: A 'b';
+///     A: 'a' | ;").unwrap();
+///   let follows = grm.follows();
+/// ```
+///
+/// then the following assertions (and only the following assertions) about the follows set are
+/// correct:
+///
+/// ```text
+///   assert!(follows.is_set(grm.nonterm_idx("S").unwrap(), grm.eof_term_idx()));
+///   assert!(follows.is_set(grm.nonterm_idx("A").unwrap(), grm.term_idx("b").unwrap()));
+/// ```
+#[derive(Debug)]
+pub struct Follows {
+    /// A mapping from `NTIdx` -> `TIdx` -> `bool` recording whether each terminal is in each
+    /// nonterminal's follows set.
+    follows: Vec<Vec<bool>>
+}
+
+impl Follows {
+    /// Generates and returns the follows set for the given grammar.
+    pub fn new(grm: &YaccGrammar) -> Follows {
+        let firsts = grm.firsts();
+        let mut follows = vec![vec![false; grm.terms_len() as usize]; grm.nonterms_len() as usize];
+        // The start rule is followed by the EOF terminal.
+        follows[usize::from(grm.start_rule_idx())][usize::from(grm.eof_term_idx())] = true;
+
+        // Loop until we reach a fixed point. For every production `A: ... B beta`, everything in
+        // FIRST(beta) is in FOLLOW(B); if beta can derive the empty string, everything in
+        // FOLLOW(A) is also in FOLLOW(B).
+        loop {
+            let mut changed = false;
+            for nt_idx in grm.iter_nonterm_idxs() {
+                for &p_idx in grm.nonterm_to_prods(nt_idx) {
+                    let prod = grm.prod(p_idx);
+                    for (i, sym) in prod.iter().enumerate() {
+                        let o_nt_idx = match *sym {
+                            Symbol::Nonterm(o_nt_idx) => o_nt_idx,
+                            Symbol::Term(_) => continue
+                        };
+                        let (seq, eps) = firsts.seq_firsts(&prod[i + 1..]);
+                        for j in 0..grm.terms_len() as usize {
+                            let b = seq[j] || (eps && follows[usize::from(nt_idx)][j]);
+                            if b && !follows[usize::from(o_nt_idx)][j] {
+                                follows[usize::from(o_nt_idx)][j] = true;
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+            }
+            if !changed {
+                return Follows{follows};
+            }
+        }
+    }
+
+    /// Return all the follows for nonterminal `nt_idx` as a mapping from `TIdx` -> `bool`.
+    pub fn follows(&self, nt_idx: NTIdx) -> &[bool] {
+        &self.follows[usize::from(nt_idx)]
+    }
+
+    /// Returns true if the terminal `t_idx` is in the follows set for nonterminal `nt_idx`.
+    pub fn is_set(&self, nt_idx: NTIdx, t_idx: TIdx) -> bool {
+        self.follows[usize::from(nt_idx)][usize::from(t_idx)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Follows;
+    use {Grammar, TIdx};
+    use yacc::{yacc_grm, YaccGrammar, YaccKind};
+
+    fn has(grm: &YaccGrammar, follows: &Follows, rn: &str, should_be: Vec<&str>) {
+        let nt_i = grm.nonterm_idx(rn).unwrap();
+        for i in 0..grm.terms_len() as usize {
+            let t_idx = TIdx::from(i);
+            let n = if t_idx == grm.eof_term_idx() {
+                "$"
+            } else if Some(t_idx) == grm.error_term_idx() {
+                "error"
+            } else {
+                grm.term_name(t_idx).unwrap()
+            };
+            match should_be.iter().position(|x| x == &n) {
+                Some(_) => {
+                    if !follows.is_set(nt_i, t_idx) {
+                        panic!("{} is not set in {}", n, rn);
+                    }
+                }
+                None    => {
+                    if follows.is_set(nt_i, t_idx) {
+                        panic!("{} is incorrectly set in {}", n, rn);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_follow() {
+        // Adapted from p2 of https://www.cs.uaf.edu/~cs331/notes/FirstFollow.pdf
+        let grm = yacc_grm(YaccKind::Original, "
+            %start E
+            %%
+            E: T E2 ;
+            E2: '+' T E2 | ;
+            T: F T2 ;
+            T2: '*' F T2 | ;
+            F: '(' E ')' | 'ID' ;
+          ").unwrap();
+        let follows = grm.follows();
+        has(&grm, &follows, "E", vec![")", "$"]);
+        has(&grm, &follows, "E2", vec![")", "$"]);
+        has(&grm, &follows, "T", vec!["+", ")", "$"]);
+        has(&grm, &follows, "T2", vec!["+", ")", "$"]);
+        has(&grm, &follows, "F", vec!["+", "*", ")", "$"]);
+    }
+
+    #[test]
+    fn test_follow_nullable_suffix() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: B C 'a' | B;
+            B: 'b';
+            C: 'c' | ;
+          ").unwrap();
+        let follows = grm.follows();
+        has(&grm, &follows, "A", vec!["$"]);
+        has(&grm, &follows, "B", vec!["a", "c", "$"]);
+        has(&grm, &follows, "C", vec!["a"]);
+    }
+
+    #[test]
+    fn test_follow_error() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: A B ';' | ;
+            B: 'b' | error;
+          ").unwrap();
+        let follows = grm.follows();
+        has(&grm, &follows, "A", vec!["b", "error", "$"]);
+        has(&grm, &follows, "B", vec![";"]);
+    }
+}
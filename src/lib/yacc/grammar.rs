@@ -31,20 +31,25 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt;
 
-use {Grammar, NTIdx, PIdx, Symbol, TIdx};
+use {Grammar, NTIdx, PIdx, SIdx, Span, Symbol, TIdx};
 use super::YaccKind;
 
 const START_NONTERM         : &str = "^";
 const IMPLICIT_NONTERM      : &str = "~";
 const IMPLICIT_START_NONTERM: &str = "^~";
+// As in Bison, terminals which aren't explicitly given a number are numbered from 258 upwards
+// (numbers below that are conventionally used for single characters and special tokens).
+const FIRST_TERM_NUMBER     : u32 = 258;
 
 use yacc::ast;
 use yacc::ast::GrammarValidationError;
-use yacc::parser::YaccParserError;
+use yacc::firsts::Firsts;
+use yacc::follows::Follows;
+use yacc::parser::{YaccParserError, YaccParserWarning};
 
 pub type PrecedenceLevel = u64;
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -71,18 +76,53 @@ pub struct YaccGrammar {
     /// A mapping from `NTIdx` -> `String`.
     nonterm_names: Vec<String>,
     /// A mapping from `TIdx` -> `Option<String>`. Every user-specified terminal will have a name,
-    /// but terminals inserted by cfgrammar (e.g. the EOF terminal) won't.
+    /// but terminals inserted by cfgrammar (e.g. the EOF terminal) won't. User-specified terminals
+    /// are numbered in the order they're declared (or, if not declared, first used) in.
     term_names: Vec<Option<String>>,
+    /// A mapping from `NTIdx` -> `Option<String>`: the type (if any) given to each nonterminal by
+    /// `%type`.
+    nonterm_types: Vec<Option<String>>,
+    /// A mapping from `TIdx` -> `Option<String>`: the type (if any) given to each terminal by
+    /// `%token <T>` or `%type`.
+    term_types: Vec<Option<String>>,
+    /// A mapping from `NTIdx` -> `Option<Span>`: the span of the rule's name where it is first
+    /// defined (for instances of parameterised rules, the parameterised rule's name), or of the
+    /// EBNF expression a synthetic rule was desugared from (`None` for nonterminals inserted by
+    /// cfgrammar).
+    nonterm_spans: Vec<Option<Span>>,
+    /// A mapping from `NTIdx` -> `bool`: is this nonterminal's rule synthesised from an EBNF
+    /// expression (e.g. `X*`) in the user's grammar?
+    nonterm_synthetic: Vec<bool>,
+    /// A mapping from `TIdx` -> `Option<Span>`: the span where the terminal is first declared or
+    /// used (`None` for terminals inserted by cfgrammar).
+    term_spans: Vec<Option<Span>>,
+    /// A mapping from `TIdx` -> `Option<Span>`: the span of the terminal in its precedence
+    /// declaration (if any).
+    term_prec_spans: Vec<Option<Span>>,
+    /// A mapping from `TIdx` -> `Option<String>`: the alias (if any) given to each terminal by
+    /// `%token NAME "alias"`.
+    term_aliases: Vec<Option<String>>,
+    /// A mapping from `TIdx` -> `Option<u32>`: the number (either given explicitly with
+    /// `%token NAME NUMBER` or allocated automatically) of each user-specified terminal.
+    term_numbers: Vec<Option<u32>>,
     /// A mapping from `TIdx` -> `Option<Precedence>`
     term_precs: Vec<Option<Precedence>>,
     /// How many terminals does this grammar have?
     terms_len: u32,
     /// The offset of the EOF terminal.
     eof_term_idx: TIdx,
+    /// The offset of the built-in `error` terminal (`None` if the grammar doesn't use it).
+    error_term_idx: Option<TIdx>,
+    /// The productions which contain the `error` terminal, in `PIdx` order.
+    error_prods: Vec<PIdx>,
     /// How many productions does this grammar have?
     prods_len: u32,
-    /// Which production is the sole production of the start rule?
-    start_prod: PIdx,
+    /// The productions of the start rule: one for each of the user's start rules, in the order
+    /// they were given in `%start`.
+    start_prods: Vec<PIdx>,
+    /// The user's start rules, in the order they were given in `%start` (so `start_prods[i]` is
+    /// the start production for `start_nonterms[i]`).
+    start_nonterms: Vec<NTIdx>,
     /// A list of all productions.
     prods: Vec<Vec<Symbol>>,
     /// A mapping from rules to their productions. Note that 1) the order of rules is identical to
@@ -93,9 +133,43 @@ pub struct YaccGrammar {
     prods_rules: Vec<NTIdx>,
     /// The precedence of each production.
     prod_precs: Vec<Option<Precedence>>,
+    /// A mapping from `PIdx` -> `Option<Span>`: the span of each production (`None` for
+    /// productions inserted by cfgrammar).
+    prod_spans: Vec<Option<Span>>,
+    /// A mapping from `PIdx` -> `Vec<Option<Span>>`: the span of each symbol in each production
+    /// (`None` for symbols inserted by cfgrammar).
+    prod_sym_spans: Vec<Vec<Option<Span>>>,
+    /// A mapping from `PIdx` -> `Option<Span>`: the span of the terminal referenced by each
+    /// production's `%prec` (if any).
+    prod_prec_spans: Vec<Option<Span>>,
+    /// A mapping from `PIdx` -> `Vec<Option<String>>`: the label (if any) of each symbol in each
+    /// production.
+    prod_labels: Vec<Vec<Option<String>>>,
+    /// A mapping from `PIdx` -> `Option<String>`: the name (if any) of each production.
+    prod_names: Vec<Option<String>>,
+    /// The action code of each production (if any).
+    actions: Vec<Option<String>>,
     /// The index of the nonterminal added for implicit tokens, if they were specified; otherwise
     /// `None`.
-    implicit_nonterm: Option<NTIdx>
+    implicit_nonterm: Option<NTIdx>,
+    /// A mapping from `NTIdx` -> `bool` recording whether each nonterminal can derive the empty
+    /// string.
+    nullables: Vec<bool>,
+    /// The number of shift/reduce conflicts the grammar author expects (via `%expect`), if
+    /// specified.
+    expect: Option<usize>,
+    /// The number of reduce/reduce conflicts the grammar author expects (via `%expect-rr`), if
+    /// specified.
+    expectrr: Option<usize>,
+    /// The contents of each `%{ ... %}` block, in the order they appeared in the source grammar.
+    prologues: Vec<String>,
+    /// The contents of the `%union { ... }` declaration, if there was one.
+    union: Option<String>,
+    /// The programs section (i.e. everything after the second `%%`) and its offset in the source
+    /// grammar, if there was one.
+    programs: Option<(usize, String)>,
+    /// Warnings from any lints enabled when the grammar was parsed.
+    warnings: Vec<YaccParserWarning>
 }
 
 // Internally, we assume that a grammar's start rule has a single production. Since we manually
@@ -109,6 +183,14 @@ impl YaccGrammar {
     /// refer to as `^`, though the actual name is a fresh name that is guaranteed to be unique)
     /// that references the user defined start rule.
     pub fn new(yacc_kind: YaccKind, ast: &ast::GrammarAST) -> YaccGrammar {
+        // %inline rules don't become nonterminals: we substitute them into a copy of the AST.
+        let inlined;
+        let ast = if ast.inline_rules.is_empty() {
+            ast
+        } else {
+            inlined = ast.inline();
+            &inlined
+        };
         let mut nonterm_names: Vec<String> = Vec::with_capacity(ast.rules.len() + 1);
 
         // Generate a guaranteed unique start nonterm name. We simply keep making the string longer
@@ -120,13 +202,15 @@ impl YaccGrammar {
             start_nonterm += START_NONTERM;
         }
         nonterm_names.push(start_nonterm.clone());
+        let starts = ast.start.iter().chain(&ast.extra_starts).collect::<Vec<_>>();
 
         let implicit_nonterm;
-        let implicit_start_nonterm;
+        // In Eco grammars with implicit tokens, each of the user's start rules has an
+        // intermediate start rule (in the same order as `starts`).
+        let mut implicit_start_nonterms = Vec::new();
         match yacc_kind {
             YaccKind::Original => {
                 implicit_nonterm = None;
-                implicit_start_nonterm = None;
             },
             YaccKind::Eco => {
                 if ast.implicit_tokens.is_some() {
@@ -137,15 +221,16 @@ impl YaccGrammar {
                     nonterm_names.push(n1.clone());
                     implicit_nonterm = Some(n1);
                     let mut n2 = IMPLICIT_START_NONTERM.to_string();
-                    while ast.rules.get(&n2).is_some() {
-                        n2 += IMPLICIT_START_NONTERM;
+                    for _ in &starts {
+                        while ast.rules.get(&n2).is_some() || implicit_start_nonterms.contains(&n2) {
+                            n2 += IMPLICIT_START_NONTERM;
+                        }
+                        nonterm_names.push(n2.clone());
+                        implicit_start_nonterms.push(n2.clone());
                     }
-                    nonterm_names.push(n2.clone());
-                    implicit_start_nonterm = Some(n2);
                 }
                 else {
                     implicit_nonterm = None;
-                    implicit_start_nonterm = None;
                 }
             }
         };
@@ -160,8 +245,8 @@ impl YaccGrammar {
             nonterm_map.insert(v.clone(), NTIdx::from(i));
         }
 
-        let mut term_names: Vec<Option<String>> = Vec::with_capacity(ast.tokens.len() + 1);
-        let mut term_precs: Vec<Option<Precedence>> = Vec::with_capacity(ast.tokens.len() + 1);
+        let mut term_names: Vec<Option<String>> = Vec::with_capacity(ast.tokens.len() + 2);
+        let mut term_precs: Vec<Option<Precedence>> = Vec::with_capacity(ast.tokens.len() + 2);
         for k in &ast.tokens {
             term_names.push(Some(k.clone()));
             term_precs.push(ast.precs.get(k).cloned());
@@ -169,6 +254,34 @@ impl YaccGrammar {
         let eof_term_idx = TIdx::from(term_names.len());
         term_names.push(None);
         term_precs.push(None);
+        // Like the EOF terminal, the built-in error terminal has no name (so that lexers need not
+        // generate it), though productions refer to it as `error`. So that grammars which don't
+        // use it have the same terminals as they would otherwise, it's only added if needed.
+        let mut error_term_idx = None;
+        if ast.rules.values().flatten().any(|&i| ast.prods[i].symbols.contains(&ast::Symbol::Error)) {
+            error_term_idx = Some(TIdx::from(term_names.len()));
+            term_names.push(None);
+            term_precs.push(None);
+        }
+        let term_aliases = term_names.iter()
+                                     .map(|n| n.as_ref().and_then(|n| ast.token_aliases.get(n).cloned()))
+                                     .collect();
+        let used_numbers = ast.token_numbers.values().cloned().collect::<HashSet<_>>();
+        let mut next_number = FIRST_TERM_NUMBER;
+        let mut term_numbers = Vec::with_capacity(term_names.len());
+        for n in &term_names {
+            match n.as_ref().map(|n| ast.token_numbers.get(n)) {
+                None => term_numbers.push(None),
+                Some(Some(&x)) => term_numbers.push(Some(x)),
+                Some(None) => {
+                    while used_numbers.contains(&next_number) {
+                        next_number += 1;
+                    }
+                    term_numbers.push(Some(next_number));
+                    next_number += 1;
+                }
+            }
+        }
         let mut term_map = HashMap::<String, TIdx>::new();
         for (i, v) in term_names.iter().enumerate() {
             if let Some(n) = v.as_ref() {
@@ -181,40 +294,46 @@ impl YaccGrammar {
         // of the list of productions.
         let mut prods = vec![None; ast.prods.len()];
         let mut prod_precs: Vec<Option<Option<Precedence>>> = vec![None; ast.prods.len()];
+        let mut actions: Vec<Option<Option<String>>> = vec![None; ast.prods.len()];
         let mut prods_rules = vec![None; ast.prods.len()];
         for astrulename in &nonterm_names {
             let rule_idx = nonterm_map[astrulename];
             if astrulename == &start_nonterm {
-                // Add the special start rule which has a single production which references a
-                // single nonterminal.
-                rules_prods[usize::from(nonterm_map[astrulename])]
-                    .push(prods.len().into());
-                let start_prod = match implicit_start_nonterm {
-                    None => {
-                        // Add ^: S;
-                        vec![Symbol::Nonterm(nonterm_map[ast.start.as_ref().unwrap()])]
-                    }
-                    Some(ref s) => {
-                        // An implicit rule has been specified, so the special start rule
-                        // needs to reference the intermediate start rule required. Therefore add:
-                        //   ^: ^~;
-                        vec![Symbol::Nonterm(nonterm_map[s])]
-                    }
-                };
-                prods.push(Some(start_prod));
-                prod_precs.push(Some(None));
-                prods_rules.push(Some(rule_idx));
+                // Add the special start rule which has, for each of the user's start rules, a
+                // production which references a single nonterminal.
+                for (i, s) in starts.iter().enumerate() {
+                    rules_prods[usize::from(nonterm_map[astrulename])]
+                        .push(prods.len().into());
+                    let start_prod = match implicit_start_nonterms.get(i) {
+                        None => {
+                            // Add ^: S;
+                            vec![Symbol::Nonterm(nonterm_map[*s])]
+                        }
+                        Some(s) => {
+                            // An implicit rule has been specified, so the special start rule
+                            // needs to reference the intermediate start rule required. Therefore
+                            // add:
+                            //   ^: ^~;
+                            vec![Symbol::Nonterm(nonterm_map[s])]
+                        }
+                    };
+                    prods.push(Some(start_prod));
+                    prod_precs.push(Some(None));
+                    actions.push(Some(None));
+                    prods_rules.push(Some(rule_idx));
+                }
                 continue;
             }
-            else if implicit_start_nonterm.as_ref().map_or(false, |s| s == astrulename) {
+            else if let Some(i) = implicit_start_nonterms.iter().position(|s| s == astrulename) {
                 // Add the intermediate start rule (handling implicit tokens at the beginning of
                 // the file):
                 //   ^~: ~ S;
                 rules_prods[usize::from(nonterm_map[astrulename])]
                     .push(prods.len().into());
                 prods.push(Some(vec![Symbol::Nonterm(nonterm_map[implicit_nonterm.as_ref().unwrap()]),
-                                     Symbol::Nonterm(nonterm_map[ast.start.as_ref().unwrap()])]));
+                                     Symbol::Nonterm(nonterm_map[starts[i]])]));
                 prod_precs.push(Some(None));
+                actions.push(Some(None));
                 prods_rules.push(Some(rule_idx));
                 continue;
             }
@@ -226,12 +345,14 @@ impl YaccGrammar {
                     implicit_prods.push(prods.len().into());
                     prods.push(Some(vec![Symbol::Term(term_map[t]), Symbol::Nonterm(rule_idx)]));
                     prod_precs.push(Some(None));
+                    actions.push(Some(None));
                     prods_rules.push(Some(rule_idx));
                 }
                 // Add an empty production
                 implicit_prods.push(prods.len().into());
                 prods.push(Some(vec![]));
                 prod_precs.push(Some(None));
+                actions.push(Some(None));
                 prods_rules.push(Some(rule_idx));
                 continue;
             }
@@ -241,52 +362,150 @@ impl YaccGrammar {
                 let astprod = &ast.prods[prod_idx];
                 let mut prod = Vec::with_capacity(astprod.symbols.len());
                 for astsym in &astprod.symbols {
-                    match *astsym {
+                    let t_idx = match *astsym {
                         ast::Symbol::Nonterm(ref n) => {
                             prod.push(Symbol::Nonterm(nonterm_map[n]));
+                            continue;
                         },
-                        ast::Symbol::Term(ref n) => {
-                            prod.push(Symbol::Term(term_map[n]));
-                            if implicit_nonterm.is_some() {
-                                prod.push(Symbol::Nonterm(nonterm_map[&implicit_nonterm.clone().unwrap()]));
-                            }
-                        }
+                        ast::Symbol::Term(ref n) => term_map[n],
+                        ast::Symbol::Error => error_term_idx.unwrap()
                     };
+                    prod.push(Symbol::Term(t_idx));
+                    if implicit_nonterm.is_some() {
+                        prod.push(Symbol::Nonterm(nonterm_map[&implicit_nonterm.clone().unwrap()]));
+                    }
                 }
                 let mut prec = None;
                 if let Some(ref n) = astprod.precedence {
                     prec = Some(ast.precs[n]);
                 } else {
+                    // The error terminal can't be given a precedence.
                     for astsym in astprod.symbols.iter().rev() {
-                        if let ast::Symbol::Term(ref n) = *astsym {
-                            if let Some(p) = ast.precs.get(n) {
-                                prec = Some(*p);
-                            }
-                            break;
+                        match *astsym {
+                            ast::Symbol::Term(ref n) => {
+                                prec = ast.precs.get(n).cloned();
+                                break;
+                            },
+                            ast::Symbol::Error => break,
+                            ast::Symbol::Nonterm(_) => ()
                         }
                     }
                 }
                 (*rule).push(prod_idx.into());
                 prods[prod_idx] = Some(prod);
                 prod_precs[prod_idx] = Some(prec);
+                actions[prod_idx] = Some(astprod.action.clone());
                 prods_rules[prod_idx] = Some(rule_idx);
             }
         }
 
+        let prods = prods.into_iter().map(|x| x.unwrap()).collect::<Vec<_>>();
+        let nullables = nonterm_nullables(&rules_prods, &prods);
+        let error_prods = match error_term_idx {
+            Some(e) => prods.iter()
+                            .enumerate()
+                            .filter(|&(_, prod)| prod.contains(&Symbol::Term(e)))
+                            .map(|(i, _)| PIdx::from(i))
+                            .collect(),
+            None => Vec::new()
+        };
+        // Productions from the AST have the same index in the grammar, so their spans can be
+        // copied across, taking care to skip over any implicit nonterminals we inserted. Any
+        // productions beyond the end of the AST's productions were created by us and have no spans.
+        let mut prod_spans = Vec::with_capacity(prods.len());
+        let mut prod_sym_spans = Vec::with_capacity(prods.len());
+        let mut prod_prec_spans = Vec::with_capacity(prods.len());
+        let mut prod_labels = Vec::with_capacity(prods.len());
+        let mut prod_names = Vec::with_capacity(prods.len());
+        for (i, prod) in prods.iter().enumerate() {
+            if i < ast.prods.len() {
+                let astprod = &ast.prods[i];
+                let mut sym_spans = Vec::with_capacity(prod.len());
+                let mut labels = Vec::with_capacity(prod.len());
+                for ((astsym, span), label) in astprod.symbols.iter()
+                                                              .zip(&astprod.symbol_spans)
+                                                              .zip(&astprod.labels) {
+                    sym_spans.push(Some(*span));
+                    labels.push(label.clone());
+                    match *astsym {
+                        ast::Symbol::Nonterm(_) => (),
+                        ast::Symbol::Term(_) | ast::Symbol::Error => {
+                            if implicit_nonterm.is_some() {
+                                sym_spans.push(None);
+                                labels.push(None);
+                            }
+                        }
+                    }
+                }
+                prod_spans.push(Some(astprod.span));
+                prod_sym_spans.push(sym_spans);
+                prod_prec_spans.push(astprod.precedence_span);
+                prod_labels.push(labels);
+                prod_names.push(astprod.name.clone());
+            } else {
+                prod_spans.push(None);
+                prod_sym_spans.push(vec![None; prod.len()]);
+                prod_prec_spans.push(None);
+                prod_labels.push(vec![None; prod.len()]);
+                prod_names.push(None);
+            }
+        }
+        let nonterm_spans = nonterm_names.iter()
+                                         .map(|n| ast.rule_spans.get(n).cloned())
+                                         .collect();
+        let nonterm_synthetic = nonterm_names.iter()
+                                             .map(|n| ast.synthetic_rules.contains(n))
+                                             .collect();
+        let term_spans = term_names.iter()
+                                   .map(|n| n.as_ref().and_then(|n| ast.token_spans.get(n).cloned()))
+                                   .collect();
+        let term_prec_spans = term_names.iter()
+                                        .map(|n| n.as_ref().and_then(|n| ast.prec_spans.get(n).cloned()))
+                                        .collect();
+        let nonterm_types = nonterm_names.iter()
+                                         .map(|n| ast.types.get(n).cloned())
+                                         .collect();
+        let term_types = term_names.iter()
+                                   .map(|n| n.as_ref().and_then(|n| ast.types.get(n).cloned()))
+                                   .collect();
         YaccGrammar{
             nonterms_len:     u32::try_from(nonterm_names.len()).unwrap(),
             nonterm_names,
             terms_len:        u32::try_from(term_names.len()).unwrap(),
             eof_term_idx,
+            error_term_idx,
+            error_prods,
             term_names,
+            nonterm_types,
+            term_types,
+            nonterm_spans,
+            nonterm_synthetic,
+            term_spans,
+            term_prec_spans,
+            term_aliases,
+            term_numbers,
             term_precs,
             prods_len:        u32::try_from(prods.len()).unwrap(),
-            start_prod:       rules_prods[usize::from(nonterm_map[&start_nonterm])][0],
+            start_prods:      rules_prods[usize::from(nonterm_map[&start_nonterm])].clone(),
+            start_nonterms:   starts.iter().map(|s| nonterm_map[*s]).collect(),
             rules_prods,
             prods_rules:      prods_rules.into_iter().map(|x| x.unwrap()).collect(),
-            prods:            prods.into_iter().map(|x| x.unwrap()).collect(),
+            prods,
             prod_precs:       prod_precs.into_iter().map(|x| x.unwrap()).collect(),
-            implicit_nonterm: implicit_nonterm.and_then(|x| Some(nonterm_map[&x]))
+            prod_spans,
+            prod_sym_spans,
+            prod_prec_spans,
+            prod_labels,
+            prod_names,
+            actions:          actions.into_iter().map(|x| x.unwrap()).collect(),
+            implicit_nonterm: implicit_nonterm.and_then(|x| Some(nonterm_map[&x])),
+            nullables,
+            expect:           ast.expect,
+            expectrr:         ast.expectrr,
+            prologues:        ast.prologues.clone(),
+            union:            ast.union.clone(),
+            programs:         ast.programs.clone(),
+            warnings:         ast.warnings.clone()
         }
     }
 
@@ -295,6 +514,18 @@ impl YaccGrammar {
         self.eof_term_idx
     }
 
+    /// Return the index of the built-in `error` terminal, which productions can use (as Yacc's
+    /// `error`) for error recovery, or `None` if no production uses it (in which case the grammar
+    /// has no such terminal). As with the end terminal, it has no name.
+    pub fn error_term_idx(&self) -> Option<TIdx> {
+        self.error_term_idx
+    }
+
+    /// Return the productions which contain the `error` terminal, in `PIdx` order.
+    pub fn error_prods(&self) -> &[PIdx] {
+        &self.error_prods
+    }
+
     /// Return the productions for nonterminal `i`. Panics if `i` doesn't exist.
     pub fn nonterm_to_prods(&self, i: NTIdx) -> &[PIdx] {
         &self.rules_prods[usize::from(i)]
@@ -305,6 +536,27 @@ impl YaccGrammar {
         &self.nonterm_names[usize::from(i)]
     }
 
+    /// Return the type given to nonterminal `i` by `%type` (where `None` indicates "no type
+    /// specified"). Panics if `i` doesn't exist.
+    pub fn nonterm_type(&self, i: NTIdx) -> Option<&str> {
+        self.nonterm_types[usize::from(i)].as_deref()
+    }
+
+    /// Return the span of the name of nonterminal `i` where its rule (or, for an instance of a
+    /// parameterised rule, the parameterised rule) is first defined or, for synthetic
+    /// nonterminals, the span of the EBNF expression it was desugared from (where
+    /// `None` indicates "this nonterminal was inserted by cfgrammar"). Panics if `i` doesn't
+    /// exist.
+    pub fn nonterm_span(&self, i: NTIdx) -> Option<Span> {
+        self.nonterm_spans[usize::from(i)]
+    }
+
+    /// Is nonterminal `i` synthetic i.e. was its rule desugared from an EBNF expression (e.g.
+    /// `X*` or `(A | B)`) in the user's grammar? Panics if `i` doesn't exist.
+    pub fn is_synthetic_nonterm(&self, i: NTIdx) -> bool {
+        self.nonterm_synthetic[usize::from(i)]
+    }
+
     /// Return an iterator which produces (in no particular order) all this grammar's valid `NTIdx`s.
     pub fn iter_nonterm_idxs(&self) -> Box<Iterator<Item=NTIdx>> {
         Box::new((0..self.nonterms_len).map(NTIdx::from))
@@ -326,12 +578,97 @@ impl YaccGrammar {
         self.prod_precs[usize::from(i)]
     }
 
+    /// Return the span of production `i` in the source grammar (where `None` indicates "this
+    /// production was inserted by cfgrammar"). Panics if `i` doesn't exist.
+    pub fn prod_span(&self, i: PIdx) -> Option<Span> {
+        self.prod_spans[usize::from(i)]
+    }
+
+    /// Return the span of symbol `j` of production `i` in the source grammar (where `None`
+    /// indicates "this symbol was inserted by cfgrammar"). Panics if `i` or `j` doesn't exist.
+    pub fn prod_sym_span(&self, i: PIdx, j: SIdx) -> Option<Span> {
+        self.prod_sym_spans[usize::from(i)][usize::from(j)]
+    }
+
+    /// Return the span of the terminal referenced by production `i`'s `%prec` (where `None`
+    /// indicates "no %prec specified"). Panics if `i` doesn't exist.
+    pub fn prod_prec_span(&self, i: PIdx) -> Option<Span> {
+        self.prod_prec_spans[usize::from(i)]
+    }
+
+    /// Return the label (e.g. `lhs` in `lhs=expr` or `expr[lhs]`) of each symbol in production
+    /// `i`, where `None` indicates "this symbol is unlabelled". Panics if `i` doesn't exist.
+    pub fn prod_labels(&self, i: PIdx) -> &[Option<String>] {
+        &self.prod_labels[usize::from(i)]
+    }
+
+    /// Return the name of production `i` (e.g. `Add` in `expr: expr '+' term #Add`) or `None` if
+    /// it wasn't named. Panics if `i` doesn't exist.
+    pub fn prod_name(&self, i: PIdx) -> Option<&str> {
+        self.prod_names[usize::from(i)].as_deref()
+    }
+
+    /// Return the action code of production `i` (excluding the outer braces) or `None` if it
+    /// has no action. Panics if `i` doesn't exist.
+    pub fn action(&self, i: PIdx) -> Option<&str> {
+        self.actions[usize::from(i)].as_deref()
+    }
+
     /// Return the name of terminal `i` (where `None` indicates "the rule has no name"). Panics if
     /// `i` doesn't exist.
     pub fn term_name(&self, i: TIdx) -> Option<&str> {
         self.term_names[usize::from(i)].as_ref().and_then(|x| Some(x.as_str()))
     }
 
+    /// Return the type given to terminal `i` by `%token <T>` or `%type` (where `None` indicates
+    /// "no type specified"). Panics if `i` doesn't exist.
+    pub fn term_type(&self, i: TIdx) -> Option<&str> {
+        self.term_types[usize::from(i)].as_deref()
+    }
+
+    /// Return the span where terminal `i` is first declared (or, if it is only declared
+    /// implicitly, first used), where `None` indicates "this terminal was inserted by cfgrammar".
+    /// Panics if `i` doesn't exist.
+    pub fn term_span(&self, i: TIdx) -> Option<Span> {
+        self.term_spans[usize::from(i)]
+    }
+
+    /// Return the span of terminal `i` in its `%left`/`%right`/`%nonassoc` declaration (where
+    /// `None` indicates "no precedence specified"). Panics if `i` doesn't exist.
+    pub fn term_prec_span(&self, i: TIdx) -> Option<Span> {
+        self.term_prec_spans[usize::from(i)]
+    }
+
+    /// Return the alias given to terminal `i` by `%token NAME "alias"` (where `None` indicates
+    /// "no alias specified"). Panics if `i` doesn't exist.
+    pub fn term_alias(&self, i: TIdx) -> Option<&str> {
+        self.term_aliases[usize::from(i)].as_deref()
+    }
+
+    /// Return the name of terminal `i` as it should be displayed to users: its alias if it has
+    /// one, or its name otherwise (where `None` indicates "the rule has no name"). Panics if `i`
+    /// doesn't exist.
+    pub fn term_display_name(&self, i: TIdx) -> Option<&str> {
+        self.term_alias(i).or_else(|| self.term_name(i))
+    }
+
+    /// Return the number of terminal `i` (where `None` indicates "this terminal was inserted by
+    /// cfgrammar and has no number"). Terminals not given an explicit number with `%token NAME
+    /// NUMBER` are numbered, in `TIdx` order, from 258 upwards, skipping explicitly given
+    /// numbers. Panics if `i` doesn't exist.
+    pub fn term_number(&self, i: TIdx) -> Option<u32> {
+        self.term_numbers[usize::from(i)]
+    }
+
+    /// Return a map from the name of each user-specified terminal to its number (see
+    /// `term_number`).
+    pub fn term_numbers_map(&self) -> HashMap<&str, u32> {
+        self.term_names.iter()
+                       .zip(&self.term_numbers)
+                       .filter_map(|(n, x)| n.as_ref().and_then(|n| x.map(|x| (n.as_str(), x))))
+                       .collect()
+    }
+
     /// Return the precedence of terminal `i` (where `None` indicates "no precedence specified").
     /// Panics if `i` doesn't exist.
     pub fn term_precedence(&self, i: TIdx) -> Option<Precedence> {
@@ -350,10 +687,93 @@ impl YaccGrammar {
         m
     }
 
-    /// Return the production index of the start rule's sole production (for Yacc grammars the
-    /// start rule is defined to have precisely one production).
+    /// Return the production index of the start rule's production for the grammar's main start
+    /// rule (i.e. the first rule given in `%start`). For Yacc grammars, the start rule is defined
+    /// to have precisely one production for each of the user's start rules.
     pub fn start_prod(&self) -> PIdx {
-        self.start_prod
+        self.start_prods[0]
+    }
+
+    /// Return the production indexes of the start rule's productions, one for each of the user's
+    /// start rules, in the order they were given in `%start`. The first is always
+    /// [`start_prod`](#method.start_prod).
+    pub fn start_prods(&self) -> &[PIdx] {
+        &self.start_prods
+    }
+
+    /// Return the index of the start rule's production for the user's start rule `i` (i.e. the
+    /// production to start parsing from in order to parse an `i`), or `None` if `i` was not given
+    /// in `%start`. Panics if `i` doesn't exist.
+    pub fn start_prod_for(&self, i: NTIdx) -> Option<PIdx> {
+        assert!(usize::from(i) < self.nonterms_len as usize);
+        self.start_nonterms.iter()
+                           .position(|&x| x == i)
+                           .map(|j| self.start_prods[j])
+    }
+
+    /// Can nonterminal `i` derive the empty string? Panics if `i` doesn't exist.
+    pub fn nullable(&self, i: NTIdx) -> bool {
+        self.nullables[usize::from(i)]
+    }
+
+    /// Return a mapping from `NTIdx` -> `bool` recording whether each nonterminal can derive the
+    /// empty string.
+    pub fn nullables(&self) -> &[bool] {
+        &self.nullables
+    }
+
+    /// Can the symbols of production `p_idx` from symbol `s_idx` onwards derive the empty string?
+    /// Note that `s_idx` can be equal to the production's length (in which case the suffix is
+    /// empty and thus trivially nullable). Panics if `p_idx` or `s_idx` doesn't exist.
+    pub fn prod_suffix_nullable(&self, p_idx: PIdx, s_idx: SIdx) -> bool {
+        self.prod(p_idx)[usize::from(s_idx)..].iter().all(|sym| {
+            match *sym {
+                Symbol::Nonterm(nt_idx) => self.nullable(nt_idx),
+                Symbol::Term(_) => false
+            }
+        })
+    }
+
+    /// Return the number of shift/reduce conflicts declared by `%expect`, or `None` if there was
+    /// no such declaration.
+    pub fn expect(&self) -> Option<usize> {
+        self.expect
+    }
+
+    /// Return the number of reduce/reduce conflicts declared by `%expect-rr`, or `None` if there
+    /// was no such declaration.
+    pub fn expectrr(&self) -> Option<usize> {
+        self.expectrr
+    }
+
+    /// Return the contents (verbatim, excluding the delimiters) of each `%{ ... %}` block, in the
+    /// order they appeared in the grammar.
+    pub fn prologues(&self) -> &[String] {
+        &self.prologues
+    }
+
+    /// Return the contents (verbatim, excluding the outer braces) of the `%union { ... }`
+    /// declaration or `None` if there wasn't one.
+    pub fn union(&self) -> Option<&str> {
+        self.union.as_deref()
+    }
+
+    /// Return the programs section of the grammar (i.e. everything after the second `%%`,
+    /// verbatim) or `None` if there wasn't one.
+    pub fn programs(&self) -> Option<&str> {
+        self.programs.as_ref().map(|x| x.1.as_str())
+    }
+
+    /// Return the byte offset of the programs section within the source grammar or `None` if
+    /// there wasn't a programs section.
+    pub fn programs_offset(&self) -> Option<usize> {
+        self.programs.as_ref().map(|x| x.0)
+    }
+
+    /// Return the warnings generated by the lints enabled in the
+    /// [`YaccOptions`](../struct.YaccOptions.html) the grammar was parsed with.
+    pub fn warnings(&self) -> &[YaccParserWarning] {
+        &self.warnings
     }
 
     /// Return the `NTIdx` of the implict nonterm if it exists, or `None` otherwise.
@@ -411,6 +831,16 @@ impl YaccGrammar {
         }
     }
 
+    /// Return the firsts set for every nonterminal in this grammar.
+    pub fn firsts(&self) -> Firsts {
+        Firsts::new(self)
+    }
+
+    /// Return the follows set for every nonterminal in this grammar.
+    pub fn follows(&self) -> Follows {
+        Follows::new(self)
+    }
+
     /// Return a `SentenceGenerator` which can then generate minimal sentences for any non-term
     /// based on the user-defined `term_cost` function which gives the associated cost for
     /// generating each terminal (where the cost must be greater than 0). Note that multiple
@@ -438,7 +868,7 @@ impl Grammar for YaccGrammar {
 
     /// Return the index of the start rule.
     fn start_rule_idx(&self) -> NTIdx {
-        self.prod_to_nonterm(self.start_prod)
+        self.prod_to_nonterm(self.start_prods[0])
     }
 }
 
@@ -652,6 +1082,38 @@ impl<'a> SentenceGenerator<'a> {
     }
 }
 
+/// Return a mapping from `NTIdx` -> `bool` recording whether each nonterminal can derive the empty
+/// string. `rules_prods` and `prods` are as in `YaccGrammar`.
+fn nonterm_nullables(rules_prods: &[Vec<PIdx>], prods: &[Vec<Symbol>]) -> Vec<bool> {
+    // A nonterminal is nullable if it has at least one production whose symbols are all nullable
+    // nonterminals (which trivially includes empty productions). We iterate until we reach a fixed
+    // point: since a nonterminal can only change from non-nullable to nullable, this terminates.
+    let mut nullables = vec![false; rules_prods.len()];
+    loop {
+        let mut changed = false;
+        for (i, p_idxs) in rules_prods.iter().enumerate() {
+            if nullables[i] {
+                continue;
+            }
+            let nullable = p_idxs.iter().any(|p_idx| {
+                prods[usize::from(*p_idx)].iter().all(|sym| {
+                    match *sym {
+                        Symbol::Nonterm(nt_idx) => nullables[usize::from(nt_idx)],
+                        Symbol::Term(_) => false
+                    }
+                })
+            });
+            if nullable {
+                nullables[i] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            return nullables;
+        }
+    }
+}
+
 /// Return the cost of a minimal string for each non-terminal in this grammar. The cost of a
 /// terminal is specified by the user-defined `term_cost` function.
 fn nonterm_min_costs(grm: &YaccGrammar, term_costs: &[u8]) -> Vec<u32>
@@ -809,46 +1271,66 @@ fn nonterm_max_costs(grm: &YaccGrammar, term_costs: &[u8]) -> Vec<u32>
     costs
 }
 
+/// The errors which prevented a `YaccGrammar` from being built. Since the parser recovers from
+/// errors, and validation checks the entire grammar, each variant contains every error found (of
+/// which there is always at least one). These variants replace the single-error
+/// `YaccParserError` and `GrammarValidationError` variants of earlier versions; code which
+/// creates a `YaccGrammarError` from a single error with `From` (or `try!`) is unaffected.
 #[derive(Debug)]
 pub enum YaccGrammarError {
-    YaccParserError(YaccParserError),
-    GrammarValidationError(GrammarValidationError)
+    YaccParserErrors(Vec<YaccParserError>),
+    GrammarValidationErrors(Vec<GrammarValidationError>)
 }
 
 impl From<YaccParserError> for YaccGrammarError {
     fn from(err: YaccParserError) -> YaccGrammarError {
-        YaccGrammarError::YaccParserError(err)
+        YaccGrammarError::YaccParserErrors(vec![err])
     }
 }
 
 impl From<GrammarValidationError> for YaccGrammarError {
     fn from(err: GrammarValidationError) -> YaccGrammarError {
-        YaccGrammarError::GrammarValidationError(err)
+        YaccGrammarError::GrammarValidationErrors(vec![err])
+    }
+}
+
+impl From<Vec<YaccParserError>> for YaccGrammarError {
+    fn from(errs: Vec<YaccParserError>) -> YaccGrammarError {
+        YaccGrammarError::YaccParserErrors(errs)
+    }
+}
+
+impl From<Vec<GrammarValidationError>> for YaccGrammarError {
+    fn from(errs: Vec<GrammarValidationError>) -> YaccGrammarError {
+        YaccGrammarError::GrammarValidationErrors(errs)
     }
 }
 
 impl fmt::Display for YaccGrammarError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            YaccGrammarError::YaccParserError(ref e) => e.fmt(f),
-            YaccGrammarError::GrammarValidationError(ref e) => e.fmt(f)
-        }
+        let errs: Vec<String> = match *self {
+            YaccGrammarError::YaccParserErrors(ref errs) => errs.iter().map(|e| e.to_string()).collect(),
+            YaccGrammarError::GrammarValidationErrors(ref errs) => errs.iter().map(|e| e.to_string()).collect()
+        };
+        write!(f, "{}", errs.join("\n"))
     }
 }
 
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
-    use super::{IMPLICIT_NONTERM, IMPLICIT_START_NONTERM, nonterm_max_costs, nonterm_min_costs};
-    use {NTIdx, PIdx, Symbol, TIdx};
-    use yacc::{AssocKind, Precedence, yacc_grm, YaccKind};
+    use super::{IMPLICIT_NONTERM, IMPLICIT_START_NONTERM, nonterm_max_costs, nonterm_min_costs,
+                START_NONTERM, YaccGrammarError};
+    use {Grammar, NTIdx, PIdx, SIdx, Span, Symbol, TIdx};
+    use yacc::{AssocKind, GrammarValidationError, GrammarValidationErrorKind, Precedence, yacc_grm,
+               yacc_grm_with_options, YaccKind, YaccOptions};
 
     #[test]
     fn test_minimal() {
         let grm = yacc_grm(YaccKind::Original,
                            "%start R %token T %% R: 'T';").unwrap();
 
-        assert_eq!(grm.start_prod, PIdx::from(1 as u32));
+        assert_eq!(grm.start_prod(), PIdx::from(1 as u32));
         assert_eq!(grm.implicit_nonterm(), None);
         grm.nonterm_idx("^").unwrap();
         grm.nonterm_idx("R").unwrap();
@@ -1064,6 +1546,451 @@ mod test {
         assert_eq!(i_prod3.len(), 0);
     }
 
+    #[test]
+    fn test_actions() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: A 'a' { $$ = $1 + 1; } | { $$ = 0; };
+            B: 'b';
+          ").unwrap();
+        let a_prods = grm.nonterm_to_prods(grm.nonterm_idx("A").unwrap());
+        assert_eq!(grm.action(a_prods[0]), Some(" $$ = $1 + 1; "));
+        assert_eq!(grm.action(a_prods[1]), Some(" $$ = 0; "));
+        assert_eq!(grm.action(grm.nonterm_to_prods(grm.nonterm_idx("B").unwrap())[0]), None);
+        assert_eq!(grm.action(grm.start_prod()), None);
+    }
+
+    #[test]
+    fn test_term_idxs_deterministic() {
+        // Terminals are numbered in the order they're declared, followed by the order they're
+        // first used in; that order must not vary from one construction to the next.
+        let src = "
+            %start A
+            %token d c
+            %implicit_tokens ws1 ws2
+            %token b
+            %left 'x'
+            %%
+            A: 'z' c B 'y' 'z' | 'a';
+            B: 'x' d 'a';
+          ";
+        let expected = vec![Some("d"), Some("c"), Some("ws1"), Some("ws2"), Some("b"), Some("x"),
+                            Some("z"), Some("y"), Some("a"), None];
+        for _ in 0..10 {
+            let grm = yacc_grm(YaccKind::Eco, src).unwrap();
+            let names = (0..grm.terms_len()).map(|i| grm.term_name(TIdx::from(i)))
+                                            .collect::<Vec<_>>();
+            assert_eq!(names, expected);
+            assert_eq!(grm.eof_term_idx(), TIdx::from(9 as u32));
+            let implicit = grm.implicit_nonterm().unwrap();
+            let firsts = grm.nonterm_to_prods(implicit)
+                            .iter()
+                            .map(|&p| grm.prod(p).to_vec())
+                            .collect::<Vec<_>>();
+            assert_eq!(firsts, vec![vec![Symbol::Term(TIdx::from(2 as u32)), Symbol::Nonterm(implicit)],
+                                    vec![Symbol::Term(TIdx::from(3 as u32)), Symbol::Nonterm(implicit)],
+                                    vec![]]);
+        }
+    }
+
+    #[test]
+    fn test_term_aliases() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start E
+            %token NUM PLUS \"+\" TIMES \"*\"
+            %left \"+\"
+            %left TIMES
+            %%
+            E: E \"+\" E | E TIMES E | NUM;
+          ").unwrap();
+        let plus = grm.term_idx("PLUS").unwrap();
+        let num = grm.term_idx("NUM").unwrap();
+        assert_eq!(grm.term_idx("+"), None);
+        assert_eq!(grm.term_name(plus), Some("PLUS"));
+        assert_eq!(grm.term_alias(plus), Some("+"));
+        assert_eq!(grm.term_display_name(plus), Some("+"));
+        assert_eq!(grm.term_display_name(grm.term_idx("TIMES").unwrap()), Some("*"));
+        assert_eq!(grm.term_alias(num), None);
+        assert_eq!(grm.term_display_name(num), Some("NUM"));
+        assert_eq!(grm.term_display_name(grm.eof_term_idx()), None);
+        assert!(grm.term_precedence(plus).is_some());
+        assert_eq!(grm.terms_len(), 4);
+        assert_eq!(grm.terms_map()["PLUS"], plus);
+        assert!(!grm.terms_map().contains_key("+"));
+    }
+
+    #[test]
+    fn test_term_numbers() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %token a 259 b c 258 d e 0
+            %%
+            A: a b c d e 'f';
+          ").unwrap();
+        let num = |n| grm.term_number(grm.term_idx(n).unwrap()).unwrap();
+        assert_eq!(num("a"), 259);
+        assert_eq!(num("b"), 260);
+        assert_eq!(num("c"), 258);
+        assert_eq!(num("d"), 261);
+        assert_eq!(num("e"), 0);
+        assert_eq!(num("f"), 262);
+        assert_eq!(grm.term_number(grm.eof_term_idx()), None);
+        let mut expected = HashMap::new();
+        for &(n, x) in &[("a", 259), ("b", 260), ("c", 258), ("d", 261), ("e", 0), ("f", 262)] {
+            expected.insert(n, x);
+        }
+        assert_eq!(grm.term_numbers_map(), expected);
+    }
+
+    #[test]
+    fn test_ebnf() {
+        let src = "
+            %start A
+            %%
+            A: 'a'? B* (B 'c' | 'd')+;
+            B: 'b';
+          ";
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        let span_str = |sp: Option<Span>| &src[sp.unwrap().start()..sp.unwrap().end()];
+        let a = grm.nonterm_idx("A").unwrap();
+        assert!(!grm.is_synthetic_nonterm(a));
+        assert!(!grm.is_synthetic_nonterm(grm.nonterm_idx("B").unwrap()));
+        assert!(!grm.is_synthetic_nonterm(grm.start_rule_idx()));
+        let syms = grm.prod(grm.nonterm_to_prods(a)[0])
+                      .iter()
+                      .map(|s| match *s {
+                          Symbol::Nonterm(i) => i,
+                          Symbol::Term(_) => panic!()
+                      })
+                      .collect::<Vec<_>>();
+        assert_eq!(syms.iter().map(|&i| grm.nonterm_name(i)).collect::<Vec<_>>(),
+                   vec!["'a'?", "B*", "(B 'c' | 'd')+"]);
+        assert!(syms.iter().all(|&i| grm.is_synthetic_nonterm(i)));
+        assert_eq!(syms.iter().map(|&i| span_str(grm.nonterm_span(i))).collect::<Vec<_>>(),
+                   vec!["'a'?", "B*", "(B 'c' | 'd')+"]);
+        let group = grm.nonterm_idx("(B 'c' | 'd')").unwrap();
+        assert!(grm.is_synthetic_nonterm(group));
+        assert_eq!(span_str(grm.nonterm_span(group)), "(B 'c' | 'd')");
+        assert_eq!(span_str(grm.prod_span(grm.nonterm_to_prods(group)[0])), "(B 'c' | 'd')");
+    }
+
+    #[test]
+    fn test_parameterised_rules() {
+        let src = "
+            %start A
+            %%
+            sep_list(sep, X): X | sep_list(sep, X) sep X;
+            A: sep_list(',', B) | opt(sep_list(';', B)) 'c'*;
+            opt(X): | X;
+            B: 'b' opt('c'*);
+          ";
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        let names = grm.iter_nonterm_idxs()
+                       .map(|i| grm.nonterm_name(i))
+                       .collect::<Vec<_>>();
+        assert_eq!(names, vec!["^", "A", "'c'*", "B", "sep_list(',', B)",
+                               "opt(sep_list(';', B))", "opt('c'*)", "sep_list(';', B)"]);
+        let list = grm.nonterm_idx("sep_list(',', B)").unwrap();
+        let prods = grm.nonterm_to_prods(list);
+        assert_eq!(prods.len(), 2);
+        assert_eq!(grm.prod(prods[1]),
+                   &[Symbol::Nonterm(list), Symbol::Term(grm.term_idx(",").unwrap()),
+                     Symbol::Nonterm(grm.nonterm_idx("B").unwrap())][..]);
+        assert!(!grm.is_synthetic_nonterm(list));
+        let span_str = |sp: Option<Span>| &src[sp.unwrap().start()..sp.unwrap().end()];
+        assert_eq!(grm.nonterm_span(list).unwrap().start(), src.find("sep_list(sep").unwrap());
+        assert_eq!(span_str(grm.prod_span(prods[1])), "sep_list(sep, X) sep X");
+
+        // Synthetic rules inside parameterised rules are instantiated with names as if the
+        // arguments had been written in place of the parameters.
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: list('a') | list(B) | 'a'*;
+            list(X): X* | '(' (X ',')+ ')';
+            B: ;
+          ").unwrap();
+        let mut names = grm.iter_nonterm_idxs()
+                           .map(|i| grm.nonterm_name(i))
+                           .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["'a'*", "('a' ',')", "('a' ',')+", "(B ',')", "(B ',')+", "A", "B",
+                               "B*", "^", "list('a')", "list(B)"]);
+        assert!(grm.is_synthetic_nonterm(grm.nonterm_idx("(B ',')+").unwrap()));
+        assert!(!grm.is_synthetic_nonterm(grm.nonterm_idx("list(B)").unwrap()));
+
+        // The same synthetic rule can be shared by templates which declare their parameters in
+        // different orders.
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: f('a', 'b') | g('b', 'a');
+            f(X, Y): (X Y)*;
+            g(Y, X): (X Y)*;
+          ").unwrap();
+        let pair = grm.nonterm_idx("('a' 'b')").unwrap();
+        assert_eq!(grm.prod(grm.nonterm_to_prods(pair)[0]),
+                   &[Symbol::Term(grm.term_idx("a").unwrap()),
+                     Symbol::Term(grm.term_idx("b").unwrap())][..]);
+        assert!(grm.nonterm_idx("('b' 'a')").is_none());
+        assert!(grm.nonterm_idx("('b' 'a')*").is_none());
+    }
+
+    #[test]
+    fn test_bad_parameterised_rules() {
+        let check = |src: &str, msg: &str| {
+            match yacc_grm(YaccKind::Original, src) {
+                Err(e @ YaccGrammarError::GrammarValidationErrors(_)) => assert_eq!(e.to_string(), msg),
+                _ => panic!()
+            }
+        };
+        check("%token x %% A: f(x); f(x): x;",
+              "Parameter 'x' has the same name as a rule or token");
+        check("%% A: f('a', 'b') | g('a') | B('a') | f; f(X): X; B: ;",
+              "Rule 'f' applied to the wrong number of arguments
+Unknown reference to rule 'g'
+Rule 'B' applied to the wrong number of arguments
+Rule 'f' applied to the wrong number of arguments");
+        check("%% A: f('a'); f(X): X | g(X); g(Y): f((Y Y));",
+              "Expansion of rule 'g' does not terminate");
+        check("%% A: f('a'); f(X): X | f(X*);",
+              "Expansion of rule 'f' does not terminate");
+        check("%start f %% A: ; f(X): X;",
+              "Start rule 'f' does not appear in grammar");
+
+        // Parameters that grow, but which aren't passed back to the rule, are fine.
+        yacc_grm(YaccKind::Original, "%% A: f('a'); f(X): X | g((X X)) | g(f(X)); g(Y): Y;").unwrap();
+    }
+
+    #[test]
+    fn test_inline_rules() {
+        let src = "
+            %start E
+            %token '+' '*'
+            %left '+'
+            %left '*'
+            %%
+            E: E binop E { x } | 'id' | '-' E %prec '*' | E op2;
+            %inline binop: '+' | '*';
+            %inline op2: binop '!' | '?' %prec '+';
+          ";
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        assert!(grm.nonterm_idx("binop").is_none());
+        assert!(grm.nonterm_idx("op2").is_none());
+        let e = grm.nonterm_idx("E").unwrap();
+        let t = |n| Symbol::Term(grm.term_idx(n).unwrap());
+        let prods = grm.nonterm_to_prods(e);
+        assert_eq!(prods.iter().map(|&p| grm.prod(p).to_vec()).collect::<Vec<_>>(),
+                   vec![vec![Symbol::Nonterm(e), t("+"), Symbol::Nonterm(e)],
+                        vec![Symbol::Nonterm(e), t("*"), Symbol::Nonterm(e)],
+                        vec![t("id")],
+                        vec![t("-"), Symbol::Nonterm(e)],
+                        vec![Symbol::Nonterm(e), t("+"), t("!")],
+                        vec![Symbol::Nonterm(e), t("*"), t("!")],
+                        vec![Symbol::Nonterm(e), t("?")]]);
+        // The precedence of an inlined production is that of its rightmost terminal, unless it or
+        // the inlined productions have a %prec.
+        let prec = |i: usize| grm.prod_precedence(prods[i]).map(|p| p.level);
+        assert_eq!(prec(0), Some(0));
+        assert_eq!(prec(1), Some(1));
+        assert_eq!(prec(3), Some(1));
+        assert_eq!(prec(4), None);
+        assert_eq!(prec(6), Some(0));
+        assert_eq!(grm.action(prods[1]), Some(" x "));
+        let span_str = |sp: Option<Span>| &src[sp.unwrap().start()..sp.unwrap().end()];
+        assert_eq!(span_str(grm.prod_span(prods[1])), "E binop E { x }");
+        assert_eq!(span_str(grm.prod_sym_span(prods[1], SIdx::from(1 as u32))), "'*'");
+        assert_eq!(span_str(grm.prod_sym_span(prods[1], SIdx::from(2 as u32))), "E");
+
+        match yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: B | C;
+            %inline B: 'b' { x } | C;
+            %inline C: 'c' B;
+          ") {
+            Err(e @ YaccGrammarError::GrammarValidationErrors(_)) => {
+                assert_eq!(e.to_string(), "%inline rule 'B' is recursive
+%inline rule 'B' has an action
+%inline rule 'C' is recursive");
+            },
+            _ => panic!()
+        }
+        match yacc_grm(YaccKind::Original, "%start A %% %inline A: 'a';") {
+            Err(e @ YaccGrammarError::GrammarValidationErrors(_)) => {
+                assert_eq!(e.to_string(), "Start rule 'A' cannot be %inline");
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn test_types() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %token <Box<Node>> a
+            %token c
+            %type <Vec<u32>> A
+            %%
+            A: a B c;
+            B: ;
+          ").unwrap();
+        assert_eq!(grm.nonterm_type(grm.nonterm_idx("A").unwrap()), Some("Vec<u32>"));
+        assert_eq!(grm.nonterm_type(grm.nonterm_idx("B").unwrap()), None);
+        assert_eq!(grm.nonterm_type(grm.start_rule_idx()), None);
+        assert_eq!(grm.term_type(grm.term_idx("a").unwrap()), Some("Box<Node>"));
+        assert_eq!(grm.term_type(grm.term_idx("c").unwrap()), None);
+        assert_eq!(grm.term_type(grm.eof_term_idx()), None);
+    }
+
+    #[test]
+    fn test_prologues_and_union() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %{ use std::fmt; %}
+            %union { u32 x; }
+            %start A
+            %{ use std::io; %}
+            %%
+            A: 'a';
+          ").unwrap();
+        assert_eq!(grm.prologues(), &[" use std::fmt; ".to_string(), " use std::io; ".to_string()]);
+        assert_eq!(grm.union(), Some(" u32 x; "));
+
+        let grm = yacc_grm(YaccKind::Original, "%start A %% A: 'a';").unwrap();
+        assert!(grm.prologues().is_empty());
+        assert_eq!(grm.union(), None);
+    }
+
+    #[test]
+    fn test_spans() {
+        let src = "
+            %start A
+            %left '+'
+            %%
+            A: A '+' B %prec '+' | B;
+            B: 'b';
+          ";
+        let span_str = |sp: Option<Span>| &src[sp.unwrap().start()..sp.unwrap().end()];
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        let a = grm.nonterm_idx("A").unwrap();
+        assert_eq!(span_str(grm.nonterm_span(a)), "A");
+        assert_eq!(grm.nonterm_span(grm.start_rule_idx()), None);
+        let plus = grm.term_idx("+").unwrap();
+        // '+' is declared by its precedence declaration.
+        assert_eq!(grm.term_span(plus).unwrap().start(), src.find("'+'").unwrap());
+        assert_eq!(span_str(grm.term_prec_span(plus)), "'+'");
+        assert_eq!(grm.term_span(grm.term_idx("b").unwrap()).unwrap().start(),
+                   src.find("'b'").unwrap());
+        assert_eq!(grm.term_span(grm.eof_term_idx()), None);
+        assert_eq!(grm.term_prec_span(grm.term_idx("b").unwrap()), None);
+
+        let p = grm.nonterm_to_prods(a)[0];
+        assert_eq!(span_str(grm.prod_span(p)), "A '+' B %prec '+'");
+        assert_eq!(span_str(grm.prod_sym_span(p, SIdx::from(2 as u32))), "B");
+        assert_eq!(grm.prod_prec_span(p).unwrap().start(), src.find("'+' |").unwrap());
+        assert_eq!(grm.prod_prec_span(grm.nonterm_to_prods(a)[1]), None);
+        assert_eq!(grm.prod_span(grm.start_prod()), None);
+        assert_eq!(grm.prod_sym_span(grm.start_prod(), SIdx::from(0 as u32)), None);
+
+        // Implicit nonterminals inserted after terminals have no spans, but the spans of the
+        // user's symbols are unaffected.
+        let src = "
+            %start A
+            %implicit_tokens ws
+            %%
+            A: 'a' B;
+            B: 'b';
+          ";
+        let span_str = |sp: Option<Span>| &src[sp.unwrap().start()..sp.unwrap().end()];
+        let grm = yacc_grm(YaccKind::Eco, src).unwrap();
+        let p = grm.nonterm_to_prods(grm.nonterm_idx("A").unwrap())[0];
+        assert_eq!(grm.prod(p).len(), 3);
+        assert_eq!(span_str(grm.prod_sym_span(p, SIdx::from(0 as u32))), "'a'");
+        assert_eq!(grm.prod_sym_span(p, SIdx::from(1 as u32)), None);
+        assert_eq!(span_str(grm.prod_sym_span(p, SIdx::from(2 as u32))), "B");
+        assert_eq!(span_str(grm.prod_span(p)), "'a' B");
+    }
+
+    #[test]
+    fn test_programs() {
+        let src = "
+            %start A
+            %%
+            A: 'a';
+            %%
+            fn f() {}
+          ";
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        assert_eq!(grm.programs(), Some("\n            fn f() {}\n          "));
+        assert_eq!(&src[grm.programs_offset().unwrap()..], grm.programs().unwrap());
+
+        let grm = yacc_grm(YaccKind::Original, "%start A %% A: 'a';").unwrap();
+        assert_eq!(grm.programs(), None);
+        assert_eq!(grm.programs_offset(), None);
+    }
+
+    #[test]
+    fn test_multiple_errors() {
+        match yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: B 'a' | C;
+            B: ;
+          ") {
+            Err(YaccGrammarError::GrammarValidationErrors(ref errs)) if errs.len() == 1 => (),
+            _ => panic!()
+        }
+
+        match yacc_grm(YaccKind::Original, "
+            %start X
+            %%
+            A: B 'a' | C %prec 'a';
+          ") {
+            Err(e @ YaccGrammarError::GrammarValidationErrors(_)) => {
+                assert_eq!(e.to_string(), "Start rule 'X' does not appear in grammar
+Unknown reference to rule 'B'
+Token 'a' used in %prec has no precedence attached
+Unknown reference to rule 'C'");
+            },
+            _ => panic!()
+        }
+
+        match yacc_grm(YaccKind::Original, "
+            %token 'a
+            %%
+            A: :;
+          ") {
+            Err(YaccGrammarError::YaccParserErrors(ref errs)) => assert_eq!(errs.len(), 2),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn test_single_error_from() {
+        let err = GrammarValidationError{kind: GrammarValidationErrorKind::NoStartRule,
+                                         sym: None,
+                                         span: None};
+        match YaccGrammarError::from(err) {
+            e @ YaccGrammarError::GrammarValidationErrors(_) => {
+                assert_eq!(e.to_string(), "No start rule specified");
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn test_expect() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %expect 1
+            %%
+            A: 'a';
+          ").unwrap();
+        assert_eq!(grm.expect(), Some(1));
+        assert_eq!(grm.expectrr(), None);
+    }
+
     #[test]
     fn test_has_path() {
         let grm = yacc_grm(YaccKind::Original, "
@@ -1087,6 +2014,35 @@ mod test {
         assert!(!grm.has_path(c_nt_idx, a_nt_idx));
     }
 
+    #[test]
+    fn test_nullable() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start A
+            %%
+            A: B C | 'a';
+            B: 'b' | ;
+            C: B B | 'c';
+            D: 'd' D | A 'd';
+            E: E 'e' | B;
+          ").unwrap();
+
+        assert!(grm.nullable(grm.nonterm_idx("A").unwrap()));
+        assert!(grm.nullable(grm.nonterm_idx("B").unwrap()));
+        assert!(grm.nullable(grm.nonterm_idx("C").unwrap()));
+        assert!(!grm.nullable(grm.nonterm_idx("D").unwrap()));
+        assert!(grm.nullable(grm.nonterm_idx("E").unwrap()));
+        assert!(grm.nullable(grm.start_rule_idx()));
+        assert_eq!(grm.nullables().iter().filter(|x| **x).count(), 5);
+
+        let d_prod = grm.nonterm_to_prods(grm.nonterm_idx("D").unwrap())[1];
+        assert!(!grm.prod_suffix_nullable(d_prod, SIdx::from(0 as u32)));
+        assert!(!grm.prod_suffix_nullable(d_prod, SIdx::from(1 as u32)));
+        assert!(grm.prod_suffix_nullable(d_prod, SIdx::from(2 as u32)));
+        let a_prod = grm.nonterm_to_prods(grm.nonterm_idx("A").unwrap())[0];
+        assert!(grm.prod_suffix_nullable(a_prod, SIdx::from(0 as u32)));
+        assert!(grm.prod_suffix_nullable(a_prod, SIdx::from(1 as u32)));
+    }
+
     #[test]
     fn test_nonterm_min_costs() {
         let grm = yacc_grm(YaccKind::Original, "
@@ -1208,4 +2164,213 @@ mod test {
                                          NTIdx::from(2 as u32),
                                          NTIdx::from(0 as u32)]);
     }
+
+    #[test]
+    fn test_multiple_starts() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start P E
+            %%
+            P: P ';' E | E;
+            E: E '+' 'id' | 'id';
+            ").unwrap();
+        assert_eq!(grm.start_prods().len(), 2);
+        assert_eq!(grm.start_prods()[0], grm.start_prod());
+        let p_idx = grm.nonterm_idx("P").unwrap();
+        let e_idx = grm.nonterm_idx("E").unwrap();
+        assert_eq!(grm.start_rule_idx(), grm.nonterm_idx(START_NONTERM).unwrap());
+        for (&nt_idx, &prod_idx) in [p_idx, e_idx].iter().zip(grm.start_prods()) {
+            assert_eq!(grm.start_prod_for(nt_idx), Some(prod_idx));
+            assert_eq!(grm.prod_to_nonterm(prod_idx), grm.start_rule_idx());
+            assert_eq!(grm.prod(prod_idx), &[Symbol::Nonterm(nt_idx)]);
+        }
+
+        // With implicit tokens, each start rule gets its own intermediate start rule.
+        let grm = yacc_grm(YaccKind::Eco, "
+            %implicit_tokens ws
+            %start P E
+            %%
+            P: P ';' E | E;
+            E: E '+' 'id' | 'id';
+            ").unwrap();
+        let e_idx = grm.nonterm_idx("E").unwrap();
+        assert_eq!(grm.start_prod_for(grm.nonterm_idx("P").unwrap()), Some(grm.start_prods()[0]));
+        let e_prod = grm.start_prod_for(e_idx).unwrap();
+        assert_eq!(e_prod, grm.start_prods()[1]);
+        let itfs = match grm.prod(e_prod) {
+            &[Symbol::Nonterm(nt_idx)] => nt_idx,
+            _ => panic!()
+        };
+        assert!(itfs != grm.nonterm_idx(IMPLICIT_START_NONTERM).unwrap());
+        let itfs_prods = grm.nonterm_to_prods(itfs);
+        assert_eq!(itfs_prods.len(), 1);
+        assert_eq!(grm.prod(itfs_prods[0]),
+                   &[Symbol::Nonterm(grm.implicit_nonterm().unwrap()), Symbol::Nonterm(e_idx)]);
+
+        // A rule that isn't a start rule has no start production.
+        let grm = yacc_grm(YaccKind::Original, "%start A %% A: B; B: 'b';").unwrap();
+        assert_eq!(grm.start_prods().len(), 1);
+        assert_eq!(grm.start_prod_for(grm.nonterm_idx("B").unwrap()), None);
+
+        match yacc_grm(YaccKind::Original, "%start A B %% A: 'a';") {
+            Err(e @ YaccGrammarError::GrammarValidationErrors(_)) =>
+                assert_eq!(e.to_string(), "Start rule 'B' does not appear in grammar"),
+            _ => panic!()
+        }
+        match yacc_grm(YaccKind::Original, "%start A B %% A: B; %inline B: 'b';") {
+            Err(e @ YaccGrammarError::GrammarValidationErrors(_)) =>
+                assert_eq!(e.to_string(), "Start rule 'B' cannot be %inline"),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn test_implicit_empty_warnings() {
+        let src = "%% A: B 'a'? | %empty; B: 'b' | ;";
+        let grm = yacc_grm(YaccKind::Original, src).unwrap();
+        assert!(grm.warnings().is_empty());
+
+        // The empty productions synthesised for 'a'? are not warned about.
+        let opts = YaccOptions{warn_implicit_empty: true, ..Default::default()};
+        let grm = yacc_grm_with_options(YaccKind::Original, opts, src).unwrap();
+        assert_eq!(grm.warnings().iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+                   vec!["Empty production not marked with %empty at line 1 column 33"]);
+        let a_prods = grm.nonterm_to_prods(grm.nonterm_idx("A").unwrap());
+        assert!(grm.prod(a_prods[1]).is_empty());
+        assert_eq!(grm.prod_span(a_prods[1]), Some(Span::new(15, 21)));
+    }
+
+    #[test]
+    fn test_labels() {
+        let grm = yacc_grm(YaccKind::Eco, "
+            %implicit_tokens ws
+            %%
+            E: lhs=E '+' T[rhs] | T;
+            T: 'id';
+            ").unwrap();
+        let e_prods = grm.nonterm_to_prods(grm.nonterm_idx("E").unwrap());
+        // The implicit nonterminal inserted after '+' is unlabelled.
+        assert_eq!(grm.prod_labels(e_prods[0]),
+                   &[Some("lhs".to_string()), None, None, Some("rhs".to_string())]);
+        assert_eq!(grm.prod_labels(e_prods[1]), &[None]);
+        assert_eq!(grm.prod_labels(grm.start_prod()), &[None]);
+
+        // Symbols substituted from %inline rules are unlabelled.
+        let grm = yacc_grm(YaccKind::Original, "%% E: x=B y='c'; %inline B: 'a' | 'b' z='d';").unwrap();
+        let e_prods = grm.nonterm_to_prods(grm.nonterm_idx("E").unwrap());
+        assert_eq!(grm.prod_labels(e_prods[0]), &[None, Some("y".to_string())]);
+        assert_eq!(grm.prod_labels(e_prods[1]), &[None, None, Some("y".to_string())]);
+
+        match yacc_grm(YaccKind::Original, "%% E: x=E '+' x='id' | y='id' | z=f('a', 'b'); f(X, Y): X Y;") {
+            Err(e @ YaccGrammarError::GrammarValidationErrors(_)) =>
+                assert_eq!(e.to_string(), "Rule 'E' has a production with a duplicate label"),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn test_production_names() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %%
+            E: E '+' T #Add | T #Term | '(' E ')';
+            T: 'id' #Id;
+            ").unwrap();
+        let e_prods = grm.nonterm_to_prods(grm.nonterm_idx("E").unwrap());
+        assert_eq!(grm.prod_name(e_prods[0]), Some("Add"));
+        assert_eq!(grm.prod_name(e_prods[1]), Some("Term"));
+        assert_eq!(grm.prod_name(e_prods[2]), None);
+        assert_eq!(grm.prod_name(grm.nonterm_to_prods(grm.nonterm_idx("T").unwrap())[0]),
+                   Some("Id"));
+        assert_eq!(grm.prod_name(grm.start_prod()), None);
+
+        // Names need only be unique within a rule.
+        yacc_grm(YaccKind::Original, "%% A: B #X | 'a' #Y; B: 'b' #X;").unwrap();
+        // A named production which references an %inline rule with a single alternative keeps
+        // its name.
+        let grm = yacc_grm(YaccKind::Original, "%% A: B 'a' #X; %inline B: 'b' 'c';").unwrap();
+        assert_eq!(grm.prod_name(grm.nonterm_to_prods(grm.nonterm_idx("A").unwrap())[0]),
+                   Some("X"));
+
+        let check = |src: &str, msg: &str| {
+            match yacc_grm(YaccKind::Original, src) {
+                Err(e @ YaccGrammarError::GrammarValidationErrors(_)) => assert_eq!(e.to_string(), msg),
+                _ => panic!()
+            }
+        };
+        check("%% A: 'a' #X | 'b' #Y | 'c' #X; B: 'b' #X | 'c' #X;",
+              "Rule 'A' has more than one production with the same name
+Rule 'B' has more than one production with the same name");
+
+        // Every production resulting from substituting an %inline rule into a named production
+        // has that production's name.
+        let grm = yacc_grm(YaccKind::Original, "
+            %start E
+            %%
+            E: E binop E #BinOp | 'id' #Id;
+            %inline binop: '+' | '-';
+            ").unwrap();
+        let e_prods = grm.nonterm_to_prods(grm.nonterm_idx("E").unwrap());
+        assert_eq!(e_prods.iter().map(|&i| grm.prod_name(i)).collect::<Vec<_>>(),
+                   vec![Some("BinOp"), Some("BinOp"), Some("Id")]);
+        yacc_grm(YaccKind::Original, "%% A: B #X | 'c' #Y; %inline B: 'a' | 'b';").unwrap();
+        check("%% A: B #X | 'c' #X; %inline B: 'a' | 'b';",
+              "Rule 'A' has more than one production with the same name");
+    }
+
+    #[test]
+    fn test_error_term() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %token error 'x'
+            %%
+            S: S ';' T | T;
+            T: 'x' | error ';' | '(' 'error' ')';
+            ").unwrap();
+        let error = grm.error_term_idx().unwrap();
+        assert!(error != grm.eof_term_idx());
+        assert_eq!(grm.term_name(error), None);
+        assert_eq!(grm.term_number(error), None);
+        assert!(grm.terms_map().values().all(|&t_idx| t_idx != error));
+        assert_eq!(grm.terms_len(), 7);
+
+        // A quoted `'error'` is an ordinary terminal, distinct from the built-in one.
+        let quoted = grm.term_idx("error").unwrap();
+        assert!(quoted != error);
+        assert_eq!(grm.term_name(quoted), Some("error"));
+        let t_prods = grm.nonterm_to_prods(grm.nonterm_idx("T").unwrap());
+        assert_eq!(grm.prod(t_prods[1]),
+                   &[Symbol::Term(error), Symbol::Term(grm.term_idx(";").unwrap())]);
+        assert_eq!(grm.prod(t_prods[2])[1], Symbol::Term(quoted));
+        assert_eq!(grm.error_prods(), &[t_prods[1]]);
+
+        // Grammars which don't use `error` don't have an error terminal.
+        let grm = yacc_grm(YaccKind::Original, "%token error %% S: 'a';").unwrap();
+        assert_eq!(grm.error_term_idx(), None);
+        assert!(grm.error_prods().is_empty());
+        assert_eq!(grm.terms_len(), 2);
+    }
+
+    #[test]
+    fn test_validation_error_spans() {
+        let src = "%token 'b'\n%%\nA: B 'c' %prec 'b' | x='a' x='a' #X | 'a' #X;\n%inline C: 'a' {x};";
+        let errs = match yacc_grm(YaccKind::Original, src) {
+            Err(YaccGrammarError::GrammarValidationErrors(errs)) => errs,
+            _ => panic!()
+        };
+        let spans = errs.iter()
+                        .map(|e| (format!("{:?}", e.kind), e.span.map(|s| &src[s.start()..s.end()])))
+                        .collect::<Vec<_>>();
+        assert_eq!(spans, vec![("NoPrecForToken".to_string(), Some("'b'")),
+                               ("UnknownRuleRef".to_string(), Some("B")),
+                               ("DuplicateLabel".to_string(), Some("'a'")),
+                               ("InlineRuleAction".to_string(), Some("C")),
+                               ("DuplicateProductionName".to_string(), Some("'a' #X"))]);
+        assert_eq!(errs[2].span, Some(Span::new(43, 46)));
+
+        match yacc_grm(YaccKind::Original, "%% A: f(g('a')); f(X): X; g(X, Y): X Y;") {
+            Err(YaccGrammarError::GrammarValidationErrors(errs)) => {
+                assert_eq!(errs.len(), 1);
+                assert_eq!(errs[0].span, Some(Span::new(6, 15)));
+            },
+            _ => panic!()
+        }
+    }
 }
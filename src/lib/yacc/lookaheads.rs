@@ -0,0 +1,597 @@
+// Copyright (c) 2018 King's College London
+// created by the Software Development Team <http://soft-dev.org/>
+//
+// The Universal Permissive License (UPL), Version 1.0
+//
+// Subject to the condition set forth below, permission is hereby granted to any person obtaining a
+// copy of this software, associated documentation and/or data (collectively the "Software"), free
+// of charge and under any and all copyright rights in the Software, and any and all patent rights
+// owned or freely licensable by each licensor hereunder covering either (i) the unmodified
+// Software as contributed to or provided by such licensor, or (ii) the Larger Works (as defined
+// below), to deal in both
+//
+// (a) the Software, and
+// (b) any piece of software and/or hardware listed in the lrgrwrks.txt file
+// if one is included with the Software (each a "Larger Work" to which the Software is contributed
+// by such licensors),
+//
+// without restriction, including without limitation the rights to copy, create derivative works
+// of, display, perform, and distribute the Software and make, use, sell, offer for sale, import,
+// export, have made, and have sold the Software and the Larger Work(s), and to sublicense the
+// foregoing rights on either these or other terms.
+//
+// This license is subject to the following condition: The above copyright notice and either this
+// complete permission notice or at a minimum a reference to the UPL must be included in all copies
+// or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING
+// BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+// NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+// DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+use std::cmp::min;
+use std::collections::{HashMap, VecDeque};
+
+use indexmap::IndexMap;
+
+use {Grammar, NTIdx, PIdx, SIdx, StIdx, Symbol, TIdx};
+use yacc::YaccGrammar;
+use yacc::firsts::Firsts;
+use yacc::stategraph::{Item, Itemset, StateGraph};
+
+/// The lookahead sets of every reduction in a `StateGraph`. A reduction is an item whose dot is
+/// at the end of its production (e.g. `E: E '+' T .`) and, since the position of the dot is thus
+/// implicit, reductions are identified by their production alone.
+#[derive(Debug)]
+pub struct Lookaheads {
+    /// A mapping from `StIdx` -> `PIdx` -> `TIdx` -> `bool`.
+    las: Vec<IndexMap<PIdx, Vec<bool>>>
+}
+
+impl Lookaheads {
+    /// Return all the reductions of state `st_idx` and their lookahead sets (as mappings from
+    /// `TIdx` -> `bool`). Panics if `st_idx` doesn't exist.
+    pub fn reductions(&self, st_idx: StIdx) -> &IndexMap<PIdx, Vec<bool>> {
+        &self.las[usize::from(st_idx)]
+    }
+
+    /// Return the lookahead set of the reduction of production `p_idx` in state `st_idx` (as a
+    /// mapping from `TIdx` -> `bool`) or `None` if `p_idx` can't be reduced in that state. Panics
+    /// if `st_idx` doesn't exist.
+    pub fn lookahead(&self, st_idx: StIdx, p_idx: PIdx) -> Option<&[bool]> {
+        self.las[usize::from(st_idx)].get(&p_idx).map(|x| x.as_slice())
+    }
+
+    /// Is terminal `t_idx` in the lookahead set of the reduction of production `p_idx` in state
+    /// `st_idx`? Panics if `st_idx` doesn't exist.
+    pub fn is_set(&self, st_idx: StIdx, p_idx: PIdx, t_idx: TIdx) -> bool {
+        self.lookahead(st_idx, p_idx).map_or(false, |x| x[usize::from(t_idx)])
+    }
+}
+
+/// Compute LALR(1) lookaheads for the LR(0) state graph `sg` of `grm` using the algorithm from
+/// "Efficient Computation of LALR(1) Look-Ahead Sets" by DeRemer and Pennello.
+pub fn lalr1(grm: &YaccGrammar, sg: &StateGraph) -> Lookaheads {
+    let terms_len = grm.terms_len() as usize;
+
+    // The algorithm is phrased in terms of nonterminal transitions: we give each a dense index
+    // so that the relations below can be represented as simple vectors.
+    let mut trans: Vec<(StIdx, NTIdx)> = Vec::new();
+    let mut trans_map = HashMap::new();
+    for st_idx in sg.iter_stidxs() {
+        for sym in sg.edges(st_idx).keys() {
+            if let Symbol::Nonterm(nt_idx) = *sym {
+                trans_map.insert((st_idx, nt_idx), trans.len());
+                trans.push((st_idx, nt_idx));
+            }
+        }
+    }
+
+    // DR(p, A) is the set of terminals which can be shifted immediately after the transition
+    // (p, A); (p, A) reads (r, C) if C is a nullable nonterminal which can be matched immediately
+    // after (p, A). The EOF terminal directly follows each of the user's start rules.
+    let mut read = Vec::with_capacity(trans.len());
+    let mut reads = Vec::with_capacity(trans.len());
+    for &(st_idx, nt_idx) in &trans {
+        let r = sg.edge(st_idx, Symbol::Nonterm(nt_idx)).unwrap();
+        let mut dr = vec![false; terms_len];
+        let mut rds = Vec::new();
+        for sym in sg.edges(r).keys() {
+            match *sym {
+                Symbol::Term(t_idx) => dr[usize::from(t_idx)] = true,
+                Symbol::Nonterm(o_nt_idx) => {
+                    if grm.nullable(o_nt_idx) {
+                        rds.push(trans_map[&(r, o_nt_idx)]);
+                    }
+                }
+            }
+        }
+        if grm.start_prods()
+              .iter()
+              .any(|&p_idx| sg.core_state(r).contains((p_idx, SIdx::from(1 as u32)))) {
+            dr[usize::from(grm.eof_term_idx())] = true;
+        }
+        read.push(dr);
+        reads.push(rds);
+    }
+    digraph(&reads, &mut read);
+
+    // (p, A) includes (p', B) if B: beta A gamma, gamma is nullable, and p' reaches p by matching
+    // beta. (q, A: omega) lookback (p, A) if p reaches q by matching omega. Both relations can be
+    // calculated by walking each production from the state it starts in.
+    let mut includes = vec![Vec::new(); trans.len()];
+    let mut lookback: HashMap<(StIdx, PIdx), Vec<usize>> = HashMap::new();
+    for (i, &(st_idx, nt_idx)) in trans.iter().enumerate() {
+        for &p_idx in grm.nonterm_to_prods(nt_idx) {
+            let mut cur = st_idx;
+            for (j, sym) in grm.prod(p_idx).iter().enumerate() {
+                if let Symbol::Nonterm(o_nt_idx) = *sym {
+                    if grm.prod_suffix_nullable(p_idx, SIdx::from(j + 1)) {
+                        includes[trans_map[&(cur, o_nt_idx)]].push(i);
+                    }
+                }
+                cur = sg.edge(cur, *sym).unwrap();
+            }
+            lookback.entry((cur, p_idx)).or_insert_with(Vec::new).push(i);
+        }
+    }
+    let mut follow = read;
+    digraph(&includes, &mut follow);
+
+    // LA(q, A: omega) is the union of Follow(p, A) for every (p, A) that (q, A: omega) looks back
+    // to. The start productions can't be looked back to (there is no transition on the start
+    // rule), but they are always followed by EOF.
+    let mut las = Vec::with_capacity(sg.all_states_len() as usize);
+    for st_idx in sg.iter_stidxs() {
+        let mut st_las = IndexMap::new();
+        for &(p_idx, s_idx) in sg.closed_state(st_idx).items() {
+            if usize::from(s_idx) != grm.prod(p_idx).len() {
+                continue;
+            }
+            let mut la = vec![false; terms_len];
+            if grm.prod_to_nonterm(p_idx) == grm.start_rule_idx() {
+                la[usize::from(grm.eof_term_idx())] = true;
+            }
+            if let Some(ts) = lookback.get(&(st_idx, p_idx)) {
+                for &t in ts {
+                    union(&mut la, &follow[t]);
+                }
+            }
+            st_las.insert(p_idx, la);
+        }
+        las.push(st_las);
+    }
+    Lookaheads{las}
+}
+
+/// Build a state graph for `grm`, and its lookaheads, using Pager's LR(1) algorithm from "A
+/// Practical General Method for Constructing LR(k) Parsers". LR(1) states with the same core are
+/// merged if they are weakly compatible, which means that the resulting state graph is often the
+/// same size as the LR(0) state graph, but never introduces the reduce/reduce conflicts that
+/// LALR(1) can.
+pub fn pager(grm: &YaccGrammar) -> (StateGraph, Lookaheads) {
+    let firsts = grm.firsts();
+
+    // Each state is represented by its (LR(0)) core and, for each item in the core, its
+    // lookahead set (its "context").
+    let mut core_states = Vec::new();
+    let mut ctxs: Vec<Vec<Vec<bool>>> = Vec::new();
+    let mut edges: Vec<IndexMap<Symbol, StIdx>> = Vec::new();
+    // A mapping from cores to all the states which have that core.
+    let mut cores_map: HashMap<Itemset, Vec<StIdx>> = HashMap::new();
+
+    // States whose contexts have grown need to be processed (again), since their successors'
+    // contexts may need to grow too.
+    let mut todo = VecDeque::new();
+    let mut in_todo = Vec::new();
+    // There is a start state (whose sole item is followed by EOF) for each start production.
+    for &p_idx in grm.start_prods() {
+        let start = Itemset::new(vec![(p_idx, SIdx::from(0 as u32))]);
+        let mut start_ctx = vec![false; grm.terms_len() as usize];
+        start_ctx[usize::from(grm.eof_term_idx())] = true;
+        let st_idx = StIdx::from(core_states.len());
+        cores_map.insert(start.clone(), vec![st_idx]);
+        core_states.push(start);
+        ctxs.push(vec![start_ctx]);
+        edges.push(IndexMap::new());
+        in_todo.push(true);
+        todo.push_back(st_idx);
+    }
+    while let Some(st_idx) = todo.pop_front() {
+        in_todo[usize::from(st_idx)] = false;
+        let closed = close_la(grm, &firsts, &core_states[usize::from(st_idx)],
+                              &ctxs[usize::from(st_idx)]);
+
+        // Gather the successor kernels (with their contexts) for each symbol.
+        let mut succs: IndexMap<Symbol, Vec<(Item, Vec<bool>)>> = IndexMap::new();
+        for (&(p_idx, s_idx), la) in &closed {
+            if let Some(&sym) = grm.prod(p_idx).get(usize::from(s_idx)) {
+                succs.entry(sym)
+                     .or_insert_with(Vec::new)
+                     .push(((p_idx, SIdx::from(u32::from(s_idx) + 1)), la.clone()));
+            }
+        }
+
+        for (sym, mut succ) in succs {
+            // Items in an Itemset are sorted, so we sort the contexts in the same order.
+            succ.sort_by_key(|x| x.0);
+            let core = Itemset::new(succ.iter().map(|x| x.0).collect());
+            let ctx = succ.into_iter().map(|x| x.1).collect::<Vec<_>>();
+            let compatible = cores_map.get(&core)
+                                      .and_then(|st_idxs| {
+                                          st_idxs.iter()
+                                                 .find(|x| weakly_compatible(&ctxs[usize::from(**x)],
+                                                                             &ctx))
+                                                 .cloned()
+                                      });
+            let target = match compatible {
+                Some(o_st_idx) => {
+                    let mut changed = false;
+                    for (ola, la) in ctxs[usize::from(o_st_idx)].iter_mut().zip(ctx.iter()) {
+                        if union(ola, la) {
+                            changed = true;
+                        }
+                    }
+                    if changed && !in_todo[usize::from(o_st_idx)] {
+                        in_todo[usize::from(o_st_idx)] = true;
+                        todo.push_back(o_st_idx);
+                    }
+                    o_st_idx
+                },
+                None => {
+                    let n_st_idx = StIdx::from(core_states.len());
+                    cores_map.entry(core.clone()).or_insert_with(Vec::new).push(n_st_idx);
+                    core_states.push(core);
+                    ctxs.push(ctx);
+                    edges.push(IndexMap::new());
+                    in_todo.push(true);
+                    todo.push_back(n_st_idx);
+                    n_st_idx
+                }
+            };
+            edges[usize::from(st_idx)].insert(sym, target);
+        }
+    }
+
+    // When a state is reprocessed, its successors may have changed, leaving some states
+    // unreachable. We remove such states, renumbering those that remain in the order they're
+    // reached from the start states (which keep their numbers).
+    let mut renum: Vec<Option<StIdx>> = vec![None; core_states.len()];
+    let mut order = Vec::new();
+    for (i, x) in renum.iter_mut().enumerate().take(grm.start_prods().len()) {
+        *x = Some(StIdx::from(i));
+        order.push(StIdx::from(i));
+    }
+    let mut i = 0;
+    while i < order.len() {
+        for &o_st_idx in edges[usize::from(order[i])].values() {
+            if renum[usize::from(o_st_idx)].is_none() {
+                renum[usize::from(o_st_idx)] = Some(StIdx::from(order.len()));
+                order.push(o_st_idx);
+            }
+        }
+        i += 1;
+    }
+    let mut n_core_states = Vec::with_capacity(order.len());
+    let mut n_closed_states = Vec::with_capacity(order.len());
+    let mut n_edges = Vec::with_capacity(order.len());
+    let mut las = Vec::with_capacity(order.len());
+    for st_idx in order {
+        let core = &core_states[usize::from(st_idx)];
+        let closed = close_la(grm, &firsts, core, &ctxs[usize::from(st_idx)]);
+        let mut st_las = IndexMap::new();
+        for (&(p_idx, s_idx), la) in &closed {
+            if usize::from(s_idx) == grm.prod(p_idx).len() {
+                st_las.insert(p_idx, la.clone());
+            }
+        }
+        las.push(st_las);
+        n_closed_states.push(core.close(grm));
+        n_core_states.push(core.clone());
+        n_edges.push(edges[usize::from(st_idx)].iter()
+                                               .map(|(&sym, &o_st_idx)|
+                                                    (sym, renum[usize::from(o_st_idx)].unwrap()))
+                                               .collect());
+    }
+    (StateGraph::from_parts(n_core_states, n_closed_states, n_edges), Lookaheads{las})
+}
+
+/// Return the LR(1) closure of the itemset `core` whose items have the lookahead sets `ctx`, as a
+/// mapping from each item in the closure to its lookahead set.
+fn close_la(grm: &YaccGrammar, firsts: &Firsts, core: &Itemset, ctx: &[Vec<bool>])
+         -> IndexMap<Item, Vec<bool>>
+{
+    let mut closed = core.items()
+                         .iter()
+                         .cloned()
+                         .zip(ctx.iter().cloned())
+                         .collect::<IndexMap<Item, Vec<bool>>>();
+    let mut todo = core.items().to_vec();
+    while let Some((p_idx, s_idx)) = todo.pop() {
+        let prod = grm.prod(p_idx);
+        let nt_idx = match prod.get(usize::from(s_idx)) {
+            Some(&Symbol::Nonterm(nt_idx)) => nt_idx,
+            _ => continue
+        };
+        // Items added for nt_idx have the lookaheads FIRST(gamma ctx) where gamma is the rest of
+        // this item's production and ctx is this item's lookahead set.
+        let (mut la, eps) = firsts.seq_firsts(&prod[usize::from(s_idx) + 1..]);
+        if eps {
+            union(&mut la, &closed[&(p_idx, s_idx)]);
+        }
+        for &o_p_idx in grm.nonterm_to_prods(nt_idx) {
+            let item = (o_p_idx, SIdx::from(0 as u32));
+            if let Some(ola) = closed.get_mut(&item) {
+                if union(ola, &la) {
+                    todo.push(item);
+                }
+                continue;
+            }
+            closed.insert(item, la.clone());
+            todo.push(item);
+        }
+    }
+    closed
+}
+
+/// Are the contexts `l` and `m` of two states with the same core weakly compatible (in Pager's
+/// sense)? i.e. for every pair of items `i` and `j`, merging the states can't introduce a
+/// reduce/reduce conflict between them that didn't exist in either state.
+fn weakly_compatible(l: &[Vec<bool>], m: &[Vec<bool>]) -> bool {
+    let disjoint = |a: &[bool], b: &[bool]| !a.iter().zip(b.iter()).any(|(x, y)| *x && *y);
+    for i in 0..l.len() {
+        for j in i + 1..l.len() {
+            if !(disjoint(&l[i], &m[j]) && disjoint(&l[j], &m[i]))
+               && disjoint(&l[i], &l[j])
+               && disjoint(&m[i], &m[j]) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Add every element of `from` to `to`, returning true if `to` changed.
+fn union(to: &mut [bool], from: &[bool]) -> bool {
+    let mut changed = false;
+    for (t, f) in to.iter_mut().zip(from.iter()) {
+        if *f && !*t {
+            *t = true;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// DeRemer and Pennello's digraph algorithm: given a relation `rel` (where `rel[x]` contains `y` if
+/// `x R y`) and initial sets `f`, update `f` such that `f[x]` is the union of its initial value and
+/// the final `f[y]` of every `y` where `x R y`. Strongly connected components are handled by
+/// Tarjan's algorithm.
+fn digraph(rel: &[Vec<usize>], f: &mut [Vec<bool>]) {
+    let mut depths = vec![0; rel.len()];
+    let mut stack = Vec::new();
+    for x in 0..rel.len() {
+        if depths[x] == 0 {
+            traverse(x, rel, f, &mut depths, &mut stack);
+        }
+    }
+}
+
+fn traverse(x: usize,
+            rel: &[Vec<usize>],
+            f: &mut [Vec<bool>],
+            depths: &mut [usize],
+            stack: &mut Vec<usize>)
+{
+    stack.push(x);
+    let d = stack.len();
+    depths[x] = d;
+    for &y in &rel[x] {
+        if depths[y] == 0 {
+            traverse(y, rel, f, depths, stack);
+        }
+        depths[x] = min(depths[x], depths[y]);
+        if x != y {
+            let fy = f[y].clone();
+            union(&mut f[x], &fy);
+        }
+    }
+    if depths[x] == d {
+        loop {
+            let z = stack.pop().unwrap();
+            depths[z] = usize::max_value();
+            if z == x {
+                break;
+            }
+            f[z] = f[x].clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lalr1, pager};
+    use {Grammar, PIdx, Symbol};
+    use yacc::{yacc_grm, YaccGrammar, YaccKind};
+    use yacc::stategraph::StateGraph;
+
+    fn prod(grm: &YaccGrammar, n: &str, i: usize) -> PIdx {
+        grm.nonterm_to_prods(grm.nonterm_idx(n).unwrap())[i]
+    }
+
+    fn la_names(grm: &YaccGrammar, la: &[bool]) -> Vec<String> {
+        let mut names = la.iter()
+                          .enumerate()
+                          .filter(|&(_, b)| *b)
+                          .map(|(i, _)| {
+                              let t_idx = i.into();
+                              if t_idx == grm.eof_term_idx() {
+                                  "$".to_string()
+                              } else if Some(t_idx) == grm.error_term_idx() {
+                                  "error".to_string()
+                              } else {
+                                  grm.term_name(t_idx).unwrap().to_string()
+                              }
+                          })
+                          .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_lalr1() {
+        // From the Dragon Book (example 4.48 in the 2nd edition): this grammar is LALR(1) but
+        // not SLR(1).
+        let grm = yacc_grm(YaccKind::Original, "
+            %start S
+            %%
+            S: L '=' R | R;
+            L: '*' R | 'id';
+            R: L;
+          ").unwrap();
+        let sg = StateGraph::new(&grm);
+        let las = lalr1(&grm, &sg);
+        assert_eq!(sg.all_states_len(), 10);
+
+        let l_st = sg.edge(sg.start_state(), Symbol::Nonterm(grm.nonterm_idx("L").unwrap()))
+                     .unwrap();
+        // In the state reached by matching L from the start state, SLR(1) would have '=' in the
+        // lookahead of R: L, but LALR(1) only has EOF.
+        assert_eq!(las.reductions(l_st).len(), 1);
+        assert_eq!(la_names(&grm, las.lookahead(l_st, prod(&grm, "R", 0)).unwrap()), vec!["$"]);
+        assert!(las.lookahead(l_st, prod(&grm, "L", 0)).is_none());
+
+        let id_st = sg.edge(sg.start_state(), Symbol::Term(grm.term_idx("id").unwrap())).unwrap();
+        assert_eq!(la_names(&grm, las.lookahead(id_st, prod(&grm, "L", 1)).unwrap()),
+                   vec!["$", "="]);
+        assert!(las.is_set(id_st, prod(&grm, "L", 1), grm.eof_term_idx()));
+
+        let s_st = sg.edge(sg.start_state(), Symbol::Nonterm(grm.nonterm_idx("S").unwrap()))
+                     .unwrap();
+        assert_eq!(la_names(&grm, las.lookahead(s_st, grm.start_prod()).unwrap()), vec!["$"]);
+    }
+
+    #[test]
+    fn test_lalr1_nullable() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start S
+            %%
+            S: A B 'c';
+            A: 'a' | ;
+            B: 'b' | ;
+          ").unwrap();
+        let sg = StateGraph::new(&grm);
+        let las = lalr1(&grm, &sg);
+        // In the start state, A's empty production can be reduced before 'a' or 'b' or 'c'.
+        let st = sg.start_state();
+        assert_eq!(la_names(&grm, las.lookahead(st, prod(&grm, "A", 1)).unwrap()),
+                   vec!["b", "c"]);
+        let a_st = sg.edge(st, Symbol::Nonterm(grm.nonterm_idx("A").unwrap())).unwrap();
+        assert_eq!(la_names(&grm, las.lookahead(a_st, prod(&grm, "B", 1)).unwrap()), vec!["c"]);
+    }
+
+    // A grammar which is LR(1) but not LALR(1): merging the states reached after 'a' 'e' and
+    // 'b' 'e' creates a reduce/reduce conflict between E: 'e' and F: 'e'.
+    fn lr1_grammar() -> YaccGrammar {
+        yacc_grm(YaccKind::Original, "
+            %start S
+            %%
+            S: 'a' E 'c' | 'a' F 'd' | 'b' F 'c' | 'b' E 'd';
+            E: 'e';
+            F: 'e';
+          ").unwrap()
+    }
+
+    #[test]
+    fn test_lalr1_merges() {
+        let grm = lr1_grammar();
+        let sg = StateGraph::new(&grm);
+        let las = lalr1(&grm, &sg);
+        let e_st = sg.edge(sg.edge(sg.start_state(), Symbol::Term(grm.term_idx("a").unwrap()))
+                             .unwrap(),
+                           Symbol::Term(grm.term_idx("e").unwrap()))
+                     .unwrap();
+        assert_eq!(la_names(&grm, las.lookahead(e_st, prod(&grm, "E", 0)).unwrap()),
+                   vec!["c", "d"]);
+        assert_eq!(la_names(&grm, las.lookahead(e_st, prod(&grm, "F", 0)).unwrap()),
+                   vec!["c", "d"]);
+    }
+
+    #[test]
+    fn test_pager_splits() {
+        let grm = lr1_grammar();
+        let lr0 = StateGraph::new(&grm);
+        let (sg, las) = pager(&grm);
+        assert_eq!(sg.all_states_len(), lr0.all_states_len() + 1);
+        let after = |t1: &str, t2: &str| {
+            sg.edge(sg.edge(sg.start_state(), Symbol::Term(grm.term_idx(t1).unwrap())).unwrap(),
+                    Symbol::Term(grm.term_idx(t2).unwrap())).unwrap()
+        };
+        let ae_st = after("a", "e");
+        let be_st = after("b", "e");
+        assert!(ae_st != be_st);
+        assert_eq!(sg.core_state(ae_st), sg.core_state(be_st));
+        assert_eq!(la_names(&grm, las.lookahead(ae_st, prod(&grm, "E", 0)).unwrap()), vec!["c"]);
+        assert_eq!(la_names(&grm, las.lookahead(ae_st, prod(&grm, "F", 0)).unwrap()), vec!["d"]);
+        assert_eq!(la_names(&grm, las.lookahead(be_st, prod(&grm, "E", 0)).unwrap()), vec!["d"]);
+        assert_eq!(la_names(&grm, las.lookahead(be_st, prod(&grm, "F", 0)).unwrap()), vec!["c"]);
+    }
+
+    #[test]
+    fn test_pager_merges() {
+        // For an LALR(1) grammar, Pager's algorithm merges states down to the same number as
+        // LR(0), with the same lookaheads as LALR(1).
+        let grm = yacc_grm(YaccKind::Original, "
+            %start E
+            %%
+            E: E '+' T | T;
+            T: T '*' F | F;
+            F: '(' E ')' | 'id';
+          ").unwrap();
+        let lr0 = StateGraph::new(&grm);
+        let lalr_las = lalr1(&grm, &lr0);
+        let (sg, las) = pager(&grm);
+        assert_eq!(sg.all_states_len(), lr0.all_states_len());
+        assert_eq!(sg.all_edges_len(), lr0.all_edges_len());
+        for st_idx in lr0.iter_stidxs() {
+            // The two graphs may number states differently, so find the equivalent state by core.
+            let o_st_idx = sg.iter_stidxs()
+                             .find(|x| sg.core_state(*x) == lr0.core_state(st_idx))
+                             .unwrap();
+            assert_eq!(lalr_las.reductions(st_idx), las.reductions(o_st_idx));
+        }
+        let id_st = sg.edge(sg.start_state(), Symbol::Term(grm.term_idx("id").unwrap())).unwrap();
+        assert_eq!(la_names(&grm, las.lookahead(id_st, prod(&grm, "F", 1)).unwrap()),
+                   vec!["$", ")", "*", "+"]);
+        assert_eq!(grm.terms_len(), 6);
+    }
+
+    #[test]
+    fn test_multiple_starts() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start P E
+            %%
+            P: P ';' E | E;
+            E: E '+' 'id' | 'id';
+          ").unwrap();
+        let lr0 = StateGraph::new(&grm);
+        let lalr_las = lalr1(&grm, &lr0);
+        let (pager_sg, pager_las) = pager(&grm);
+        for &(sg, las) in &[(&lr0, &lalr_las), (&pager_sg, &pager_las)] {
+            let p_st = sg.start_state_for(&grm, grm.start_prods()[0]).unwrap();
+            let e_st = sg.start_state_for(&grm, grm.start_prods()[1]).unwrap();
+            // After an E in the program start state, E: 'id' may be followed by ';', but not in
+            // the expression start state.
+            let id_st = sg.edge(p_st, Symbol::Term(grm.term_idx("id").unwrap())).unwrap();
+            assert_eq!(la_names(&grm, las.lookahead(id_st, prod(&grm, "E", 1)).unwrap()),
+                       vec!["$", "+", ";"]);
+            let e_after = sg.edge(e_st, Symbol::Nonterm(grm.nonterm_idx("E").unwrap())).unwrap();
+            assert_eq!(la_names(&grm, las.lookahead(e_after, grm.start_prods()[1]).unwrap()),
+                       vec!["$"]);
+            let p_after = sg.edge(p_st, Symbol::Nonterm(grm.nonterm_idx("P").unwrap())).unwrap();
+            assert_eq!(la_names(&grm, las.lookahead(p_after, grm.start_prods()[0]).unwrap()),
+                       vec!["$"]);
+        }
+    }
+}
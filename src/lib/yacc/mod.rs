@@ -31,12 +31,23 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 pub mod ast;
+pub mod conflicts;
+pub mod firsts;
+pub mod follows;
 pub mod grammar;
+pub mod lookaheads;
 pub mod parser;
+pub mod stategraph;
 pub use self::ast::{GrammarValidationError, GrammarValidationErrorKind};
-pub use self::parser::{YaccParserError, YaccParserErrorKind};
+pub use self::conflicts::{Conflict, ConflictReport, find_conflicts, resolve_conflicts, Resolution};
+pub use self::firsts::Firsts;
+pub use self::follows::Follows;
+pub use self::parser::{YaccParserError, YaccParserErrorKind, YaccParserWarning,
+                       YaccParserWarningKind};
 use self::parser::YaccParser;
 pub use self::grammar::{AssocKind, Precedence, SentenceGenerator, YaccGrammar, YaccGrammarError};
+pub use self::lookaheads::{lalr1, Lookaheads, pager};
+pub use self::stategraph::{Item, Itemset, StateGraph};
 
 /// The particular Yacc variant this grammar makes use of.
 #[derive(Clone, Copy)]
@@ -48,13 +59,35 @@ pub enum YaccKind {
     Eco
 }
 
+/// Options which customise how a Yacc grammar is parsed. New options may be added in the future,
+/// so callers should construct this struct with `YaccOptions{..., ..Default::default()}`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct YaccOptions {
+    /// If `true`, rule names and unquoted token names are restricted to ASCII letters, digits,
+    /// and `_` (and, for rule names, `.`). If `false` (the default), they may be any Unicode
+    /// identifier (i.e. an `XID_Start` character followed by `XID_Continue` characters, where `_`
+    /// may also start a name and `.` may also appear in rule names).
+    pub ascii_names: bool,
+    /// If `true`, a warning is generated for each empty production which isn't explicitly marked
+    /// with `%empty` (e.g. `A: 'a' | ;`). Warnings can be obtained from the resulting grammar with
+    /// [`YaccGrammar::warnings`](grammar/struct.YaccGrammar.html#method.warnings).
+    pub warn_implicit_empty: bool
+}
+
 /// Takes as input a Yacc grammar of [`YaccKind`](enum.YaccKind.html) as a `String` `s` and returns a
 /// [`YaccGrammar`](grammar/struct.YaccGrammar.html) (or
 /// ([`YaccGrammarError`](grammar/enum.YaccGrammarError.html) on error).
 pub fn yacc_grm(yacc_kind: YaccKind, s: &str) -> Result<YaccGrammar, YaccGrammarError> {
+    yacc_grm_with_options(yacc_kind, YaccOptions::default(), s)
+}
+
+/// As [`yacc_grm`](fn.yacc_grm.html), but parsing the grammar according to the
+/// [`YaccOptions`](struct.YaccOptions.html) `options`.
+pub fn yacc_grm_with_options(yacc_kind: YaccKind, options: YaccOptions, s: &str)
+                          -> Result<YaccGrammar, YaccGrammarError> {
     match yacc_kind {
         YaccKind::Original | YaccKind::Eco => {
-            let mut yp = YaccParser::new(yacc_kind, s.to_string());
+            let mut yp = YaccParser::new(yacc_kind, options, s.to_string());
             try!(yp.parse());
             let mut ast = yp.ast();
             try!(ast.complete_and_validate());
@@ -32,16 +32,20 @@
 
 // Note: this is the parser for both YaccKind::Original and YaccKind::Eco yacc kinds.
 
-use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fmt;
 
+use indexmap::IndexSet;
+
 extern crate regex;
 use self::regex::Regex;
 
 type YaccResult<T> = Result<T, YaccParserError>;
 
-use yacc::{AssocKind, Precedence, YaccKind};
-use yacc::ast::{GrammarAST, Symbol};
+use Span;
+use yacc::{AssocKind, Precedence, YaccKind, YaccOptions};
+use yacc::ast::{application_name, ERROR_TERM, GrammarAST, Production, Symbol, symbol_name};
+use yacc::grammar::PrecedenceLevel;
 
 /// The various different possible Yacc parser errors.
 #[derive(Debug)]
@@ -52,12 +56,34 @@ pub enum YaccParserErrorKind {
     IncompleteComment,
     MissingColon,
     PrematureEnd,
-    ProgramsNotSupported,
     UnknownDeclaration,
     DuplicatePrecedence,
     PrecNotFollowedByTerm,
     DuplicateImplicitTokensDeclaration,
-    DuplicateStartDeclaration
+    DuplicateStartDeclaration,
+    DuplicateExpectDeclaration,
+    DuplicateExpectRRDeclaration,
+    IllegalInteger,
+    IncompleteAction,
+    MidRuleAction,
+    MissingType,
+    IncompleteType,
+    IncompletePrologue,
+    IncompleteUnion,
+    DuplicateUnionDeclaration,
+    DuplicateTokenNumber,
+    DuplicateTokenAlias,
+    IllegalEscape,
+    IncompleteGroup,
+    IncompleteParameters,
+    IncompleteArguments,
+    DuplicateParameter,
+    MismatchedParameters,
+    NonEmptyProduction,
+    IncompleteLabel,
+    MultipleLabels,
+    MisplacedProductionName,
+    IllegalErrorTokenDeclaration
 }
 
 /// Any error from the Yacc parser returns an instance of this struct.
@@ -77,7 +103,6 @@ impl fmt::Display for YaccParserError {
             YaccParserErrorKind::IncompleteComment    => "Incomplete comment",
             YaccParserErrorKind::MissingColon         => "Missing colon",
             YaccParserErrorKind::PrematureEnd         => "File ends prematurely",
-            YaccParserErrorKind::ProgramsNotSupported => "Programs not currently supported",
             YaccParserErrorKind::UnknownDeclaration   => "Unknown declaration",
             YaccParserErrorKind::DuplicatePrecedence  => "Token already has a precedence",
             YaccParserErrorKind::PrecNotFollowedByTerm
@@ -86,6 +111,61 @@ impl fmt::Display for YaccParserError {
                                                       => "Duplicate %implicit_tokens declaration",
             YaccParserErrorKind::DuplicateStartDeclaration
                                                       => "Duplicate %start declaration",
+            YaccParserErrorKind::DuplicateExpectDeclaration
+                                                      => "Duplicate %expect declaration",
+            YaccParserErrorKind::DuplicateExpectRRDeclaration
+                                                      => "Duplicate %expect-rr declaration",
+            YaccParserErrorKind::IllegalInteger       => "Illegal integer",
+            YaccParserErrorKind::IncompleteAction     => "Incomplete action",
+            YaccParserErrorKind::MidRuleAction        => "Mid-rule actions not supported",
+            YaccParserErrorKind::MissingType          => "Missing type",
+            YaccParserErrorKind::IncompleteType       => "Incomplete type",
+            YaccParserErrorKind::IncompletePrologue   => "Incomplete %{ ... %} block",
+            YaccParserErrorKind::IncompleteUnion      => "Incomplete %union declaration",
+            YaccParserErrorKind::DuplicateUnionDeclaration
+                                                      => "Duplicate %union declaration",
+            YaccParserErrorKind::DuplicateTokenNumber => "Token number already in use",
+            YaccParserErrorKind::DuplicateTokenAlias  => "Token alias already in use",
+            YaccParserErrorKind::IllegalEscape        => "Illegal escape sequence",
+            YaccParserErrorKind::IncompleteGroup      => "Group not closed by ')'",
+            YaccParserErrorKind::IncompleteParameters => "Parameter list not closed by ')'",
+            YaccParserErrorKind::IncompleteArguments  => "Argument list not closed by ')'",
+            YaccParserErrorKind::DuplicateParameter   => "Duplicate parameter",
+            YaccParserErrorKind::MismatchedParameters => "Rule redefined with different parameters",
+            YaccParserErrorKind::NonEmptyProduction   => "%empty in a production with symbols",
+            YaccParserErrorKind::IncompleteLabel      => "Label not closed by ']'",
+            YaccParserErrorKind::MultipleLabels       => "Symbol has more than one label",
+            YaccParserErrorKind::MisplacedProductionName
+                                                      => "Production name not at the end of production",
+            YaccParserErrorKind::IllegalErrorTokenDeclaration
+                                                      => "Illegal declaration of the built-in error token",
+        };
+        write!(f, "{} at line {} column {}", s, self.line, self.col)
+    }
+}
+
+/// The various different possible Yacc parser warnings. Warnings are only generated by the lints
+/// enabled in [`YaccOptions`](../struct.YaccOptions.html).
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YaccParserWarningKind {
+    ImplicitEmptyProduction
+}
+
+/// Any warning from the Yacc parser is an instance of this struct.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct YaccParserWarning {
+    pub kind: YaccParserWarningKind,
+    line: usize,
+    col: usize
+}
+
+impl fmt::Display for YaccParserWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self.kind {
+            YaccParserWarningKind::ImplicitEmptyProduction
+                => "Empty production not marked with %empty",
         };
         write!(f, "{} at line {} column {}", s, self.line, self.col)
     }
@@ -93,45 +173,81 @@ impl fmt::Display for YaccParserError {
 
 pub(crate) struct YaccParser {
     yacc_kind: YaccKind,
+    options: YaccOptions,
     src: String,
     newlines: Vec<usize>,
+    errs: Vec<YaccParserError>,
+    // the parameters of the rule currently being parsed (empty if it is not parameterised)
+    rule_params: Vec<String>,
     ast: GrammarAST
 }
 
 lazy_static! {
     static ref RE_NAME: Regex = {
+        Regex::new(r"^[\p{XID_Start}_.][\p{XID_Continue}.]*").unwrap()
+    };
+    static ref RE_ASCII_NAME: Regex = {
         Regex::new(r"^[a-zA-Z_.][a-zA-Z0-9_.]*").unwrap()
     };
+    static ref RE_INT: Regex = {
+        Regex::new(r"^[0-9]+").unwrap()
+    };
     static ref RE_TERMINAL: Regex = {
-        Regex::new("^(?:(\".+?\")|('.+?')|([a-zA-Z_][a-zA-Z_0-9]*))").unwrap()
+        Regex::new(r"^[\p{XID_Start}_]\p{XID_Continue}*").unwrap()
+    };
+    static ref RE_ASCII_TERMINAL: Regex = {
+        Regex::new("^[a-zA-Z_][a-zA-Z_0-9]*").unwrap()
+    };
+    static ref RE_UNICODE_ESCAPE: Regex = {
+        Regex::new(r"^u\{([0-9a-fA-F]{1,6})\}").unwrap()
     };
 }
 
 /// The actual parser is intended to be entirely opaque from outside users.
 impl YaccParser {
-    pub (crate) fn new(yacc_kind: YaccKind, src: String) -> YaccParser {
+    pub (crate) fn new(yacc_kind: YaccKind, options: YaccOptions, src: String) -> YaccParser {
         YaccParser {
             yacc_kind,
+            options,
             src,
             newlines: vec![0],
+            errs: Vec::new(),
+            rule_params: Vec::new(),
             ast : GrammarAST::new()
         }
     }
 
-    pub(crate) fn parse(&mut self) -> YaccResult<usize> {
+    /// Parse the input, returning every error found (in the order they were found) if the input
+    /// is not a valid grammar.
+    pub(crate) fn parse(&mut self) -> Result<usize, Vec<YaccParserError>> {
+        match self.parse_grammar() {
+            Ok(i) => {
+                if self.errs.is_empty() {
+                    Ok(i)
+                } else {
+                    Err(self.errs.drain(..).collect())
+                }
+            },
+            Err(e) => {
+                self.errs.push(e);
+                Err(self.errs.drain(..).collect())
+            }
+        }
+    }
+
+    /// Parse the input. Errors from which we can recover (by skipping to the next declaration or
+    /// rule) are recorded in `self.errs`; other errors are returned immediately.
+    fn parse_grammar(&mut self) -> YaccResult<usize> {
         // We pass around an index into the *bytes* of self.src. We guarantee that at all times
         // this points to the beginning of a UTF-8 character (since multibyte characters exist, not
         // every byte within the string is also a valid character).
         let mut i = try!(self.parse_declarations(0));
         i = try!(self.parse_rules(i));
-        // We don't currently support the programs part of a specification. One day we might...
+        // Everything after a second '%%' is the programs section, which we capture verbatim.
         match self.lookahead_is("%%", i) {
             Some(j) => {
-                if try!(self.parse_ws(j)) == self.src.len() {
-                    Ok(i)
-                } else {
-                    Err(self.mk_error(YaccParserErrorKind::ProgramsNotSupported, i))
-                }
+                self.ast.programs = Some((j, self.src[j..].to_string()));
+                Ok(self.src.len())
             }
             None    => Ok(i)
         }
@@ -143,82 +259,238 @@ impl YaccParser {
 
     fn parse_declarations(&mut self, mut i: usize) -> YaccResult<usize> {
         i = try!(self.parse_ws(i));
-        let mut prec_level  = 0;
+        let mut prec_level = 0;
         while i < self.src.len() {
             if self.lookahead_is("%%", i).is_some() { return Ok(i); }
-            if let Some(j) = self.lookahead_is("%token", i) {
+            match self.parse_declaration(i, &mut prec_level) {
+                Ok(j) => i = j,
+                Err(e) => {
+                    // Skip to the start of the next declaration (or the '%%' that ends the
+                    // declarations) and carry on from there.
+                    self.errs.push(e);
+                    i = self.skip_to_declaration(i);
+                }
+            }
+        }
+        Err(self.mk_error(YaccParserErrorKind::PrematureEnd, i - 1))
+    }
+
+    /// Parse the declaration starting at `i`, returning the offset of the next declaration.
+    fn parse_declaration(&mut self, mut i: usize, prec_level: &mut PrecedenceLevel)
+                      -> YaccResult<usize> {
+        if let Some(j) = self.lookahead_is("%token", i) {
+            i = try!(self.parse_ws(j));
+            let mut ty = None;
+            if self.lookahead_is("<", i).is_some() {
+                let (j, t) = try!(self.parse_type(i));
+                ty = Some(t);
                 i = try!(self.parse_ws(j));
-                while i < self.src.len() {
-                    if self.lookahead_is("%", i).is_some() {
-                        break;
+            }
+            while i < self.src.len() {
+                if self.lookahead_is("%", i).is_some() {
+                    break;
+                }
+                let quoted = self.lookahead_is("\"", i).is_some() || self.lookahead_is("'", i).is_some();
+                let (j, n) = try!(self.parse_terminal(i));
+                // `error` is built-in, so declaring it has no effect, and it can't be given any of
+                // the properties that a declaration can give other tokens. A quoted `'error'` is an
+                // ordinary token.
+                if self.is_error_term(i) {
+                    if ty.is_some() {
+                        return Err(self.mk_error(YaccParserErrorKind::IllegalErrorTokenDeclaration, i));
                     }
-                    let (j, n) = try!(self.parse_terminal(i));
-                    self.ast.tokens.insert(n);
                     i = try!(self.parse_ws(j));
+                    if RE_INT.is_match(&self.src[i..]) || self.lookahead_is("\"", i).is_some() {
+                        return Err(self.mk_error(YaccParserErrorKind::IllegalErrorTokenDeclaration, i));
+                    }
+                    continue;
                 }
-                continue;
+                if let Some(ref t) = ty {
+                    self.ast.types.insert(n.clone(), t.clone());
+                }
+                self.ast.token_spans.entry(n.clone()).or_insert_with(|| Span::new(i, j));
+                i = try!(self.parse_ws(j));
+                // A token name may be followed by an explicit token number (e.g. `%token NUM 258`).
+                if RE_INT.is_match(&self.src[i..]) {
+                    let (j, num) = try!(self.parse_int(i));
+                    let num = match u32::try_from(num) {
+                        Ok(x) => x,
+                        Err(_) => return Err(self.mk_error(YaccParserErrorKind::IllegalInteger, i))
+                    };
+                    let in_use = self.ast.token_numbers.iter().any(|(m, &x)| x == num && *m != n);
+                    if in_use || self.ast.token_numbers.get(&n).map_or(false, |&x| x != num) {
+                        return Err(self.mk_error(YaccParserErrorKind::DuplicateTokenNumber, i));
+                    }
+                    self.ast.token_numbers.insert(n.clone(), num);
+                    i = try!(self.parse_ws(j));
+                }
+                // A token name may also be followed by a double-quoted alias (e.g. `%token PLUS
+                // "+"`), which can then be used in place of the token's name.
+                if !quoted && self.lookahead_is("\"", i).is_some() {
+                    let (j, alias) = try!(self.parse_terminal(i));
+                    let in_use = self.ast.token_aliases.iter().any(|(m, a)| *a == alias && *m != n);
+                    if in_use || self.ast.token_aliases.get(&n).map_or(false, |a| *a != alias) {
+                        return Err(self.mk_error(YaccParserErrorKind::DuplicateTokenAlias, i));
+                    }
+                    self.ast.token_aliases.insert(n.clone(), alias);
+                    i = try!(self.parse_ws(j));
+                }
+                self.ast.tokens.insert(n);
             }
-            if let Some(j) = self.lookahead_is("%start", i) {
-                if self.ast.start.is_some() {
-                    return Err(self.mk_error(YaccParserErrorKind::DuplicateStartDeclaration, i));
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%type", i) {
+            i = try!(self.parse_ws(j));
+            let (j, ty) = try!(self.parse_type(i));
+            i = try!(self.parse_ws(j));
+            while i < self.src.len() {
+                if self.lookahead_is("%", i).is_some() {
+                    break;
                 }
+                let (j, n) = try!(self.parse_token_ref(i));
+                self.ast.types.insert(n, ty.clone());
                 i = try!(self.parse_ws(j));
+            }
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%start", i) {
+            if self.ast.start.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateStartDeclaration, i));
+            }
+            i = try!(self.parse_ws(j));
+            // The first start rule is the grammar's main start rule: any others are further entry
+            // points into the grammar.
+            let (j, n) = try!(self.parse_name(i));
+            self.ast.start = Some(n);
+            i = try!(self.parse_ws(j));
+            while i < self.src.len() && self.lookahead_is("%", i).is_none() {
                 let (j, n) = try!(self.parse_name(i));
-                self.ast.start = Some(n);
+                if self.ast.start.as_ref() == Some(&n) || self.ast.extra_starts.contains(&n) {
+                    return Err(self.mk_error(YaccParserErrorKind::DuplicateStartDeclaration, i));
+                }
+                self.ast.extra_starts.push(n);
                 i = try!(self.parse_ws(j));
-                continue;
             }
-            if let YaccKind::Eco = self.yacc_kind {
-                if let Some(j) = self.lookahead_is("%implicit_tokens", i) {
-                    if self.ast.implicit_tokens.is_some() {
-                        return Err(self.mk_error(YaccParserErrorKind::DuplicateImplicitTokensDeclaration, i));
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%{", i) {
+            let (j, p) = try!(self.parse_prologue(i, j));
+            self.ast.prologues.push(p);
+            i = try!(self.parse_ws(j));
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%union", i) {
+            if self.ast.union.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateUnionDeclaration, i));
+            }
+            let k = try!(self.parse_ws(j));
+            if self.lookahead_is("{", k).is_none() {
+                return Err(self.mk_error(YaccParserErrorKind::IncompleteUnion, i));
+            }
+            let (j, u) = match self.parse_action(k) {
+                Ok(x) => x,
+                Err(_) => return Err(self.mk_error(YaccParserErrorKind::IncompleteUnion, i))
+            };
+            self.ast.union = Some(u);
+            i = try!(self.parse_ws(j));
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%expect-rr", i) {
+            if self.ast.expectrr.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateExpectRRDeclaration, i));
+            }
+            i = try!(self.parse_ws(j));
+            let (j, n) = try!(self.parse_int(i));
+            self.ast.expectrr = Some(n);
+            i = try!(self.parse_ws(j));
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%expect", i) {
+            if self.ast.expect.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateExpectDeclaration, i));
+            }
+            i = try!(self.parse_ws(j));
+            let (j, n) = try!(self.parse_int(i));
+            self.ast.expect = Some(n);
+            i = try!(self.parse_ws(j));
+            return Ok(i);
+        }
+        if let YaccKind::Eco = self.yacc_kind {
+            if let Some(j) = self.lookahead_is("%implicit_tokens", i) {
+                if self.ast.implicit_tokens.is_some() {
+                    return Err(self.mk_error(YaccParserErrorKind::DuplicateImplicitTokensDeclaration, i));
+                }
+                let mut implicit_terms = IndexSet::new();
+                i = try!(self.parse_ws(j));
+                while j < self.src.len() {
+                    if self.lookahead_is("%", i).is_some() {
+                        break;
                     }
-                    let mut implicit_terms = HashSet::new();
-                    i = try!(self.parse_ws(j));
-                    while j < self.src.len() {
-                        if self.lookahead_is("%", i).is_some() {
-                            break;
-                        }
-                        let (j, n) = try!(self.parse_terminal(i));
-                        self.ast.tokens.insert(n.clone());
-                        implicit_terms.insert(n);
-                        i = try!(self.parse_ws(j));
+                    if self.is_error_term(i) {
+                        return Err(self.mk_error(YaccParserErrorKind::IllegalErrorTokenDeclaration, i));
                     }
-                    self.ast.implicit_tokens = Some(implicit_terms);
-                    continue;
+                    let (j, n) = try!(self.parse_terminal(i));
+                    self.ast.token_spans.entry(n.clone()).or_insert_with(|| Span::new(i, j));
+                    self.ast.tokens.insert(n.clone());
+                    implicit_terms.insert(n);
+                    i = try!(self.parse_ws(j));
                 }
+                self.ast.implicit_tokens = Some(implicit_terms);
+                return Ok(i);
+            }
+        }
+        {
+            let k;
+            let kind;
+            if let Some(j) = self.lookahead_is("%left", i) {
+                kind = AssocKind::Left;
+                k = j;
+            } else if let Some(j) = self.lookahead_is("%right", i) {
+                kind = AssocKind::Right;
+                k = j;
+            } else if let Some(j) = self.lookahead_is("%nonassoc", i) {
+                kind = AssocKind::Nonassoc;
+                k = j;
+            } else {
+                return Err(self.mk_error(YaccParserErrorKind::UnknownDeclaration, i));
             }
-            {
-                let k;
-                let kind;
-                if let Some(j) = self.lookahead_is("%left", i) {
-                    kind = AssocKind::Left;
-                    k = j;
-                } else if let Some(j) = self.lookahead_is("%right", i) {
-                    kind = AssocKind::Right;
-                    k = j;
-                } else if let Some(j) = self.lookahead_is("%nonassoc", i) {
-                    kind = AssocKind::Nonassoc;
-                    k = j;
-                } else {
-                    return Err(self.mk_error(YaccParserErrorKind::UnknownDeclaration, i));
-                }
 
-                i = try!(self.parse_ws(k));
-                while i < self.src.len() {
-                    if self.lookahead_is("%", i).is_some() { break; }
-                    let (j, n) = try!(self.parse_terminal(i));
-                    if self.ast.precs.contains_key(&n) {
-                        return Err(self.mk_error(YaccParserErrorKind::DuplicatePrecedence, i));
-                    }
-                    let prec = Precedence{level: prec_level, kind};
-                    self.ast.precs.insert(n, prec);
-                    i = try!(self.parse_ws(j));
+            i = try!(self.parse_ws(k));
+            while i < self.src.len() {
+                if self.lookahead_is("%", i).is_some() { break; }
+                if self.is_error_term(i) {
+                    return Err(self.mk_error(YaccParserErrorKind::IllegalErrorTokenDeclaration, i));
                 }
-                prec_level += 1;
+                let (j, n) = try!(self.parse_token_ref(i));
+                if self.ast.precs.contains_key(&n) {
+                    return Err(self.mk_error(YaccParserErrorKind::DuplicatePrecedence, i));
+                }
+                let prec = Precedence{level: *prec_level, kind};
+                self.ast.prec_spans.insert(n.clone(), Span::new(i, j));
+                // As in Yacc, a precedence declaration also declares its tokens.
+                self.ast.token_spans.entry(n.clone()).or_insert_with(|| Span::new(i, j));
+                self.ast.tokens.insert(n.clone());
+                self.ast.precs.insert(n, prec);
+                i = try!(self.parse_ws(j));
             }
+            *prec_level += 1;
         }
-        Err(self.mk_error(YaccParserErrorKind::PrematureEnd, i - 1))
+        Ok(i)
+    }
+
+    /// Skip to the next `%` after the (erroneous) declaration starting at `i`.
+    fn skip_to_declaration(&mut self, i: usize) -> usize {
+        let mut j = i;
+        while j < self.src.len() {
+            let c = self.src[j..].chars().next().unwrap();
+            if c == '%' && j > i {
+                break;
+            } else if c == '\n' || c == '\r' {
+                self.add_newline(j + 1);
+            }
+            j += c.len_utf8();
+        }
+        j
     }
 
     fn parse_rules(&mut self, mut i: usize) -> YaccResult<usize> {
@@ -230,18 +502,90 @@ impl YaccParser {
         i = try!(self.parse_ws(i));
         while i < self.src.len() {
             if self.lookahead_is("%%", i).is_some() { break; }
-            i = try!(self.parse_rule(i));
+            match self.parse_rule(i) {
+                Ok(j) => i = j,
+                Err(e) => {
+                    // Skip to the end of the erroneous rule and carry on from there.
+                    self.errs.push(e);
+                    i = self.skip_to_rule(i);
+                }
+            }
             i = try!(self.parse_ws(i));
         }
         Ok(i)
     }
 
+    /// Skip to the offset after the `;` which ends the (erroneous) rule starting at `i` or, if
+    /// there is no such `;`, to the next `%%` or the end of the input. Semi-colons inside actions,
+    /// comments, and quoted terminals are ignored.
+    fn skip_to_rule(&mut self, i: usize) -> usize {
+        let mut j = i;
+        while j < self.src.len() {
+            j = match self.parse_ws(j) {
+                Ok(k) => k,
+                Err(_) => return self.src.len()
+            };
+            if j == self.src.len() || self.lookahead_is("%%", j).is_some() {
+                break;
+            }
+            let c = self.src[j..].chars().next().unwrap();
+            match c {
+                ';' => return j + 1,
+                '{' => {
+                    match self.parse_action(j) {
+                        Ok((k, _)) => j = k,
+                        Err(_) => return self.src.len()
+                    }
+                },
+                '\'' | '"' => {
+                    match self.parse_terminal(j) {
+                        Ok((k, _)) => j = k,
+                        Err(_) => j += c.len_utf8()
+                    }
+                },
+                _ => j += c.len_utf8()
+            }
+        }
+        j
+    }
+
     fn parse_rule(&mut self, mut i: usize) -> YaccResult<usize> {
+        let inline = match self.lookahead_is("%inline", i) {
+            Some(j) => {
+                i = try!(self.parse_ws(j));
+                true
+            },
+            None => false
+        };
         let (j, rn) = try!(self.parse_name(i));
-        if self.ast.start.is_none() {
+        if rn == ERROR_TERM {
+            return Err(self.mk_error(YaccParserErrorKind::IllegalName, i));
+        }
+        let (k, params) = if self.lookahead_is("(", j).is_some() {
+            try!(self.parse_params(j))
+        } else {
+            (j, Vec::new())
+        };
+        if self.ast.rules.contains_key(&rn)
+           && self.ast.templates.get(&rn).map_or(&[][..], |x| x.as_slice()) != params.as_slice() {
+            return Err(self.mk_error(YaccParserErrorKind::MismatchedParameters, i));
+        }
+        // Parameterised and %inline rules can't be the start rule, so the implicit start rule is
+        // the first rule which is neither.
+        if self.ast.start.is_none() && params.is_empty() && !inline {
             self.ast.start = Some(rn.clone());
         }
-        i = try!(self.parse_ws(j));
+        if !params.is_empty() {
+            self.ast.templates.insert(rn.clone(), params.clone());
+        }
+        if inline {
+            self.ast.inline_rules.insert(rn.clone());
+        }
+        self.rule_params = params;
+        self.ast.rule_spans.entry(rn.clone()).or_insert_with(|| Span::new(i, j));
+        // Make sure this rule is ordered before any synthetic rules created from its productions.
+        self.ast.rules.entry(rn.clone()).or_insert_with(Vec::new);
+        i = try!(self.parse_ws(k));
         match self.lookahead_is(":", i) {
             Some(j) => i = j,
             None    => {
@@ -249,50 +593,347 @@ impl YaccParser {
             }
         }
         let mut syms = Vec::new();
+        let mut sym_spans = Vec::new();
+        let mut labels = Vec::new();
         let mut prec = None;
+        let mut prec_span = None;
+        let mut action = None;
+        let mut empty_span = None;
+        let mut prod_name = None;
+        // The offsets of the start of the first, and the end of the last, element of the current
+        // production (if it has any elements).
+        let mut prod_start = None;
+        let mut prod_end = i;
         i = try!(self.parse_ws(i));
         while i < self.src.len() {
-            if let Some(j) = self.lookahead_is("|", i) {
-                self.ast.add_prod(rn.clone(), syms, prec);
+            let end = self.lookahead_is(";", i);
+            if self.lookahead_is("|", i).is_some() || end.is_some() {
+                let span = match prod_start {
+                    Some(s) => Span::new(s, prod_end),
+                    None => Span::new(i, i)
+                };
+                if syms.is_empty() && empty_span.is_none() && self.options.warn_implicit_empty {
+                    let (line, col) = self.off_to_line_col(i);
+                    self.ast.warnings.push(YaccParserWarning{
+                        kind: YaccParserWarningKind::ImplicitEmptyProduction, line, col});
+                }
+                self.ast.add_production(rn.clone(), Production{symbols: syms,
+                                                               precedence: prec,
+                                                               action,
+                                                               span,
+                                                               symbol_spans: sym_spans,
+                                                               precedence_span: prec_span,
+                                                               empty_span,
+                                                               labels,
+                                                               name: prod_name});
+                if let Some(j) = end {
+                    return Ok(j);
+                }
                 syms = Vec::new();
+                sym_spans = Vec::new();
+                labels = Vec::new();
                 prec = None;
-                i = try!(self.parse_ws(j));
+                prec_span = None;
+                action = None;
+                empty_span = None;
+                prod_name = None;
+                prod_start = None;
+                i = try!(self.parse_ws(i + 1));
                 continue;
-            } else if let Some(j) = self.lookahead_is(";", i) {
-                self.ast.add_prod(rn.clone(), syms, prec);
-                return Ok(j);
             }
 
-            if self.lookahead_is("\"", i).is_some() || self.lookahead_is("'", i).is_some() {
-                let (j, sym) = try!(self.parse_terminal(i));
-                i = try!(self.parse_ws(j));
-                self.ast.tokens.insert(sym.clone());
-                syms.push(Symbol::Term(sym));
-            } else if let Some(j) = self.lookahead_is("%prec", i) {
-                i = try!(self.parse_ws(j));
-                let (k, sym) = try!(self.parse_terminal(i));
-                if self.ast.tokens.contains(&sym) {
+            let j;
+            if prod_name.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::MisplacedProductionName, i));
+            } else if let Some(k) = self.lookahead_is("#", i) {
+                // A production's name (e.g. `#Add` in `E: E '+' T #Add`) comes after everything
+                // else in the production.
+                let (k, n) = try!(self.parse_label(k));
+                prod_name = Some(n);
+                j = k;
+            } else if self.lookahead_is("{", i).is_some() {
+                // We only support actions at the end of a production (though, as in Yacc, they
+                // can be followed by %prec).
+                if action.is_some() {
+                    return Err(self.mk_error(YaccParserErrorKind::MidRuleAction, i));
+                }
+                let (k, a) = try!(self.parse_action(i));
+                action = Some(a);
+                j = k;
+            } else if action.is_some() && self.lookahead_is("%prec", i).is_none() {
+                return Err(self.mk_error(YaccParserErrorKind::MidRuleAction, i));
+            } else if let Some(k) = self.lookahead_is("%prec", i) {
+                let l = try!(self.parse_ws(k));
+                let (k, sym) = try!(self.parse_token_ref(l));
+                if self.ast.tokens.contains(&sym) && !self.is_error_term(l) {
                     prec = Some(sym);
+                    prec_span = Some(Span::new(l, k));
                 } else {
-                    return Err(self.mk_error(YaccParserErrorKind::PrecNotFollowedByTerm, i));
+                    return Err(self.mk_error(YaccParserErrorKind::PrecNotFollowedByTerm, l));
+                }
+                j = k;
+            } else if let Some(k) = self.lookahead_is("%empty", i)
+                                        .or_else(|| self.lookahead_is("%epsilon", i)) {
+                // %empty (or its synonym %epsilon) must be the only symbol in its production.
+                if !syms.is_empty() || empty_span.is_some() {
+                    return Err(self.mk_error(YaccParserErrorKind::NonEmptyProduction, i));
                 }
-                i = k;
+                empty_span = Some(Span::new(i, k));
+                j = k;
             } else {
-                let (j, sym) = try!(self.parse_terminal(i));
-                if self.ast.tokens.contains(&sym) {
-                    syms.push(Symbol::Term(sym));
-                } else {
-                    syms.push(Symbol::Nonterm(sym));
+                if let Some(span) = empty_span {
+                    return Err(self.mk_error(YaccParserErrorKind::NonEmptyProduction,
+                                             span.start()));
                 }
-                i = j;
+                let (k, label, sym, span) = try!(self.parse_labelled_sym(i));
+                syms.push(sym);
+                sym_spans.push(span);
+                labels.push(label);
+                j = k;
             }
-            i = try!(self.parse_ws(i));
+            if prod_start.is_none() {
+                prod_start = Some(i);
+            }
+            prod_end = j;
+            i = try!(self.parse_ws(j));
         }
         Err(self.mk_error(YaccParserErrorKind::IncompleteRule, i))
     }
 
+    /// Parse a symbol starting at `i` which may be labelled, either as `label = sym` or (as in
+    /// Bison) as `sym[label]`, returning the offset after the symbol and its label, the label (if any),
+    /// the symbol, and the span of the symbol (excluding the label).
+    fn parse_labelled_sym(&mut self, i: usize)
+                       -> YaccResult<(usize, Option<String>, Symbol, Span)> {
+        let mut label = None;
+        let mut j = i;
+        if let Ok((k, n)) = self.parse_label(i) {
+            let k = try!(self.parse_ws(k));
+            if let Some(l) = self.lookahead_is("=", k) {
+                label = Some(n);
+                j = try!(self.parse_ws(l));
+            }
+        }
+        let (k, sym) = try!(self.parse_ebnf_sym(j));
+        let span = Span::new(j, k);
+        let mut j = k;
+        if self.lookahead_is("[", j).is_some() {
+            if label.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::MultipleLabels, j));
+            }
+            let k = try!(self.parse_ws(j + 1));
+            let (k, n) = try!(self.parse_label(k));
+            let k = try!(self.parse_ws(k));
+            match self.lookahead_is("]", k) {
+                Some(l) => j = l,
+                None => return Err(self.mk_error(YaccParserErrorKind::IncompleteLabel, j))
+            }
+            label = Some(n);
+        }
+        Ok((j, label, sym, span))
+    }
+
+    /// Parse a symbol's label (which has the same syntax as an unquoted token name) starting at `i`.
+    fn parse_label(&self, i: usize) -> YaccResult<(usize, String)> {
+        match self.src[i..].chars().next() {
+            Some('"') | Some('\'') => Err(self.mk_error(YaccParserErrorKind::IllegalName, i)),
+            _ => self.parse_terminal(i)
+                     .map_err(|_| self.mk_error(YaccParserErrorKind::IllegalName, i))
+        }
+    }
+
+    /// Parse a symbol starting at `i`, which may be a parenthesised group of alternatives, and
+    /// which may be followed by one or more of the EBNF operators `?`, `*`, and `+`. Groups and
+    /// operators are desugared into synthetic rules, in which case a reference to the synthetic
+    /// rule is returned.
+    fn parse_ebnf_sym(&mut self, i: usize) -> YaccResult<(usize, Symbol)> {
+        let (mut j, mut sym) = if self.lookahead_is("(", i).is_some() {
+            try!(self.parse_group(i))
+        } else if self.lookahead_is("\"", i).is_some() || self.lookahead_is("'", i).is_some() {
+            let (j, n) = try!(self.parse_token_ref(i));
+            self.ast.token_spans.entry(n.clone()).or_insert_with(|| Span::new(i, j));
+            self.ast.tokens.insert(n.clone());
+            (j, Symbol::Term(n))
+        } else {
+            let (j, n) = try!(self.parse_terminal(i));
+            if n == ERROR_TERM && !self.rule_params.contains(&n) {
+                (j, Symbol::Error)
+            } else if self.lookahead_is("(", j).is_some() {
+                try!(self.parse_application(j, n))
+            } else if self.ast.tokens.contains(&n) && !self.rule_params.contains(&n) {
+                (j, Symbol::Term(n))
+            } else {
+                (j, Symbol::Nonterm(n))
+            }
+        };
+        loop {
+            let k = try!(self.parse_ws(j));
+            let op = match self.src[k..].chars().next() {
+                Some(c @ '?') | Some(c @ '*') | Some(c @ '+') => c,
+                _ => break
+            };
+            let span = Span::new(i, k + 1);
+            let sym_span = Span::new(i, j);
+            let name = format!("{}{}", symbol_name(&sym), op);
+            let rec = self.synthetic_ref(&name, &[&sym]);
+            let prods = match op {
+                // X?: X | ;
+                '?' => vec![(vec![sym], vec![sym_span]), (vec![], vec![])],
+                // X*: | X* X ;
+                '*' => vec![(vec![], vec![]), (vec![rec.clone(), sym], vec![span, sym_span])],
+                // X+: X | X+ X ;
+                _ => vec![(vec![sym.clone()], vec![sym_span]), (vec![rec.clone(), sym], vec![span, sym_span])]
+            };
+            self.add_synthetic_rule(&name, span, prods);
+            sym = rec;
+            j = k + 1;
+        }
+        Ok((j, sym))
+    }
+
+    /// Parse a parenthesised group of alternatives (e.g. `(A B | C)`) starting at the `(` at `i`.
+    /// Unless the group consists of a single symbol (in which case that symbol is returned), a
+    /// synthetic rule is created for the group, and a reference to it is returned.
+    fn parse_group(&mut self, i: usize) -> YaccResult<(usize, Symbol)> {
+        let mut prods = vec![(Vec::new(), Vec::new())];
+        let mut j = try!(self.parse_ws(i + 1));
+        loop {
+            if self.lookahead_is(")", j).is_some() {
+                break;
+            } else if let Some(k) = self.lookahead_is("|", j) {
+                prods.push((Vec::new(), Vec::new()));
+                j = try!(self.parse_ws(k));
+                continue;
+            } else if self.lookahead_is("{", j).is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::MidRuleAction, j));
+            } else if j == self.src.len() || self.lookahead_is(";", j).is_some()
+                      || self.lookahead_is("%", j).is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::IncompleteGroup, i));
+            }
+            let (k, sym) = try!(self.parse_ebnf_sym(j));
+            let prod = prods.last_mut().unwrap();
+            prod.0.push(sym);
+            prod.1.push(Span::new(j, k));
+            j = try!(self.parse_ws(k));
+        }
+        let span = Span::new(i, j + 1);
+        if prods.len() == 1 && prods[0].0.len() == 1 {
+            return Ok((j + 1, prods.pop().unwrap().0.pop().unwrap()));
+        }
+        let alts = prods.iter()
+                        .map(|p| p.0.iter().map(symbol_name).collect::<Vec<_>>().join(" "))
+                        .collect::<Vec<_>>();
+        let name = format!("({})", alts.join(" | "));
+        let sym = self.synthetic_ref(&name, &prods.iter().flat_map(|p| &p.0).collect::<Vec<_>>());
+        self.add_synthetic_rule(&name, span, prods);
+        Ok((j + 1, sym))
+    }
+
+    /// Parse the parameter list (e.g. `(sep, X)`) of a parameterised rule starting at the `(` at
+    /// `i`.
+    fn parse_params(&mut self, i: usize) -> YaccResult<(usize, Vec<String>)> {
+        let mut params = Vec::new();
+        let mut j = i;
+        loop {
+            j = try!(self.parse_ws(j + 1));
+            let (k, n) = try!(self.parse_terminal(j));
+            if params.contains(&n) {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateParameter, j));
+            }
+            params.push(n);
+            j = try!(self.parse_ws(k));
+            if let Some(k) = self.lookahead_is(")", j) {
+                return Ok((k, params));
+            } else if self.lookahead_is(",", j).is_none() {
+                return Err(self.mk_error(YaccParserErrorKind::IncompleteParameters, i));
+            }
+        }
+    }
+
+    /// Parse the arguments (e.g. `(',', expr)`) of an application of the parameterised rule
+    /// `name` starting at the `(` at `i`, returning a reference to the (yet to be expanded)
+    /// instance of that rule.
+    fn parse_application(&mut self, i: usize, name: String) -> YaccResult<(usize, Symbol)> {
+        let mut args = Vec::new();
+        let mut j = i;
+        loop {
+            j = try!(self.parse_ws(j + 1));
+            let (k, sym) = try!(self.parse_ebnf_sym(j));
+            args.push(sym);
+            j = try!(self.parse_ws(k));
+            if let Some(k) = self.lookahead_is(")", j) {
+                j = k;
+                break;
+            } else if self.lookahead_is(",", j).is_none() {
+                return Err(self.mk_error(YaccParserErrorKind::IncompleteArguments, i));
+            }
+        }
+        let app_name = application_name(&name, &args);
+        self.ast.applications.insert(app_name.clone(), (name, args));
+        Ok((j, Symbol::Nonterm(app_name)))
+    }
+
+    /// Return the parameters of the current rule which `syms` refer to (directly, or via the
+    /// arguments of an application), in the order they first occur in `syms`. Since a synthetic
+    /// rule's name determines its symbols, this order depends only on the synthetic rule, and not
+    /// on the order in which the current rule declares its parameters.
+    fn mentioned_params(&self, syms: &[&Symbol]) -> Vec<String> {
+        let mut mentioned = Vec::new();
+        let mut todo = syms.iter().rev().map(|s| (*s).clone()).collect::<Vec<_>>();
+        while let Some(sym) = todo.pop() {
+            if let Symbol::Nonterm(n) = sym {
+                if self.rule_params.contains(&n) {
+                    if !mentioned.contains(&n) {
+                        mentioned.push(n);
+                    }
+                } else if let Some(app) = self.ast.applications.get(&n) {
+                    todo.extend(app.1.iter().rev().cloned());
+                }
+            }
+        }
+        mentioned
+    }
+
+    /// Return a reference to the synthetic rule `name` whose productions contain `syms`. If
+    /// `syms` refer to any of the current rule's parameters, the synthetic rule is itself
+    /// parameterised by them, and the reference is an application of it to those parameters
+    /// (which has the same name as the synthetic rule).
+    fn synthetic_ref(&mut self, name: &str, syms: &[&Symbol]) -> Symbol {
+        let params = self.mentioned_params(syms);
+        if !params.is_empty() {
+            let args = params.iter().map(|p| Symbol::Nonterm(p.clone())).collect::<Vec<_>>();
+            self.ast.applications.entry(name.to_string()).or_insert((name.to_string(), args));
+            self.ast.templates.entry(name.to_string()).or_insert(params);
+        }
+        Symbol::Nonterm(name.to_string())
+    }
+
+    /// Add a synthetic rule `name`, desugared from the EBNF expression at `span`, with the
+    /// productions (and their symbols' spans) `prods`. If the same EBNF expression has already
+    /// been desugared, the existing rule is reused.
+    fn add_synthetic_rule(&mut self, name: &str, span: Span, prods: Vec<(Vec<Symbol>, Vec<Span>)>) {
+        if self.ast.rules.contains_key(name) {
+            return;
+        }
+        for (symbols, symbol_spans) in prods {
+            let labels = vec![None; symbols.len()];
+            self.ast.add_production(name.to_string(), Production{symbols,
+                                                                 precedence: None,
+                                                                 action: None,
+                                                                 span,
+                                                                 symbol_spans,
+                                                                 precedence_span: None,
+                                                                 empty_span: None,
+                                                                 labels,
+                                                                 name: None});
+        }
+        self.ast.rule_spans.insert(name.to_string(), span);
+        self.ast.synthetic_rules.insert(name.to_string());
+    }
+
     fn parse_name(&self, i: usize) -> YaccResult<(usize, String)> {
-        match RE_NAME.find(&self.src[i..]) {
+        let re: &Regex = if self.options.ascii_names { &RE_ASCII_NAME } else { &RE_NAME };
+        match re.find(&self.src[i..]) {
             Some(m) => {
                 assert_eq!(m.start(), 0);
                 Ok((i + m.end(), self.src[i..i + m.end()].to_string()))
@@ -303,22 +944,253 @@ impl YaccParser {
         }
     }
 
-    fn parse_terminal(&self, i: usize) -> YaccResult<(usize, String)> {
-        match RE_TERMINAL.find(&self.src[i..]) {
+    /// Parse an action starting at the `{` at `i`, returning the offset after the matching `}`
+    /// and the action's code (excluding the outer braces) verbatim. Braces inside strings,
+    /// character literals, and comments in the code are ignored.
+    fn parse_action(&mut self, i: usize) -> YaccResult<(usize, String)> {
+        debug_assert!(self.lookahead_is("{", i).is_some());
+        let mut depth = 0;
+        let mut j = i;
+        while j < self.src.len() {
+            let c = self.src[j..].chars().next().unwrap();
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok((j + 1, self.src[i + 1..j].to_string()));
+                    }
+                },
+                '\n' | '\r' => self.add_newline(j + 1),
+                '"' => {
+                    j = try!(self.skip_code_delimited(i, j, '"'));
+                    continue;
+                },
+                '\'' => {
+                    // This is either a character literal or, in Rust, a lifetime (which has no
+                    // closing quote).
+                    let mut cs = self.src[j + 1..].chars();
+                    match (cs.next(), cs.next()) {
+                        (Some('\\'), _) => {
+                            j = try!(self.skip_code_delimited(i, j, '\''));
+                            continue;
+                        },
+                        (Some(d), Some('\'')) => {
+                            j += 2 + d.len_utf8();
+                            continue;
+                        },
+                        _ => ()
+                    }
+                },
+                '/' => {
+                    if self.lookahead_is("//", j).is_some() {
+                        match self.src[j..].find('\n') {
+                            Some(k) => j += k,
+                            None => break
+                        }
+                        continue;
+                    } else if self.lookahead_is("/*", j).is_some() {
+                        match self.src[j + 2..].find("*/") {
+                            Some(k) => {
+                                let nls = self.src[j..j + 2 + k].match_indices('\n')
+                                                                .map(|(l, _)| j + l + 1)
+                                                                .collect::<Vec<_>>();
+                                for nl in nls {
+                                    self.add_newline(nl);
+                                }
+                                j += 2 + k + 2;
+                            },
+                            None => break
+                        }
+                        continue;
+                    }
+                },
+                _ => ()
+            }
+            j += c.len_utf8();
+        }
+        Err(self.mk_error(YaccParserErrorKind::IncompleteAction, i))
+    }
+
+    /// Skip over a string (or character literal) in an action starting at the delimiter `delim`
+    /// at `j`, returning the offset after the closing delimiter. Backslash escapes are honoured.
+    /// `i` is the offset of the start of the action, used for error reporting.
+    fn skip_code_delimited(&mut self, i: usize, mut j: usize, delim: char) -> YaccResult<usize> {
+        j += delim.len_utf8();
+        while j < self.src.len() {
+            let c = self.src[j..].chars().next().unwrap();
+            j += c.len_utf8();
+            if c == '\\' {
+                match self.src[j..].chars().next() {
+                    Some(d) => j += d.len_utf8(),
+                    None => break
+                }
+            } else if c == delim {
+                return Ok(j);
+            } else if c == '\n' || c == '\r' {
+                self.add_newline(j);
+            }
+        }
+        Err(self.mk_error(YaccParserErrorKind::IncompleteAction, i))
+    }
+
+    /// Parse a `%{ ... %}` block whose `%{` starts at `i` and whose contents start at `j`,
+    /// returning the offset after the closing `%}` and the block's contents verbatim.
+    fn parse_prologue(&mut self, i: usize, j: usize) -> YaccResult<(usize, String)> {
+        let mut k = j;
+        while k < self.src.len() {
+            if self.lookahead_is("%}", k).is_some() {
+                return Ok((k + 2, self.src[j..k].to_string()));
+            }
+            let c = self.src[k..].chars().next().unwrap();
+            if c == '\n' || c == '\r' {
+                self.add_newline(k + 1);
+            }
+            k += c.len_utf8();
+        }
+        Err(self.mk_error(YaccParserErrorKind::IncompletePrologue, i))
+    }
+
+    /// Parse a type of the form `<T>` starting at `i`, returning the offset immediately after the
+    /// closing `>` and `T`. Angle brackets may nest (e.g. `<Vec<u8>>`), and the `>` of an arrow
+    /// (e.g. `<Box<Fn(u8) -> u8>>`) doesn't close a bracket.
+    fn parse_type(&mut self, i: usize) -> YaccResult<(usize, String)> {
+        if self.lookahead_is("<", i).is_none() {
+            return Err(self.mk_error(YaccParserErrorKind::MissingType, i));
+        }
+        let mut depth = 0;
+        let mut j = i;
+        while j < self.src.len() {
+            let c = self.src[j..].chars().next().unwrap();
+            match c {
+                '<' => depth += 1,
+                '>' if self.src[..j].ends_with('-') => (),
+                '>' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let ty = self.src[i + 1..j].trim();
+                        if ty.is_empty() {
+                            return Err(self.mk_error(YaccParserErrorKind::MissingType, i));
+                        }
+                        return Ok((j + 1, ty.to_string()));
+                    }
+                },
+                '\n' | '\r' => self.add_newline(j + 1),
+                _ => ()
+            }
+            j += c.len_utf8();
+        }
+        Err(self.mk_error(YaccParserErrorKind::IncompleteType, i))
+    }
+
+    fn parse_int(&self, i: usize) -> YaccResult<(usize, usize)> {
+        match RE_INT.find(&self.src[i..]) {
             Some(m) => {
-                assert!(m.start() == 0 && m.end() > 0);
-                match self.src[i..].chars().next().unwrap() {
-                    '"' | '\'' => {
-                        assert!('"'.len_utf8() == 1 && '\''.len_utf8() == 1);
-                        Ok((i + m.end(), self.src[i + 1..i + m.end() - 1].to_string()))
+                match self.src[i..i + m.end()].parse::<usize>() {
+                    Ok(n) => Ok((i + m.end(), n)),
+                    Err(_) => Err(self.mk_error(YaccParserErrorKind::IllegalInteger, i))
+                }
+            },
+            None => Err(self.mk_error(YaccParserErrorKind::IllegalInteger, i))
+        }
+    }
+
+    fn parse_terminal(&self, i: usize) -> YaccResult<(usize, String)> {
+        match self.src[i..].chars().next() {
+            Some(q @ '"') | Some(q @ '\'') => self.parse_quoted_terminal(i, q),
+            _ => {
+                let re: &Regex = if self.options.ascii_names { &RE_ASCII_TERMINAL } else { &RE_TERMINAL };
+                match re.find(&self.src[i..]) {
+                    Some(m) => {
+                        assert!(m.start() == 0 && m.end() > 0);
+                        Ok((i + m.end(), self.src[i..i + m.end()].to_string()))
                     },
-                    _ =>  Ok((i + m.end(), self.src[i..i + m.end()].to_string()))
+                    None => {
+                        Err(self.mk_error(YaccParserErrorKind::IllegalString, i))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parse a terminal quoted by `q` (either `'` or `"`) starting at `i`, returning the offset
+    /// after the closing quote and the terminal with any escape sequences (`\\`, `\'`, `\"`,
+    /// `\n`, `\r`, `\t`, and `\u{...}`) decoded.
+    fn parse_quoted_terminal(&self, i: usize, q: char) -> YaccResult<(usize, String)> {
+        let mut s = String::new();
+        let mut j = i + q.len_utf8();
+        while j < self.src.len() {
+            let c = self.src[j..].chars().next().unwrap();
+            match c {
+                '\n' | '\r' => break,
+                '\\' => {
+                    let (k, d) = try!(self.parse_escape(j));
+                    s.push(d);
+                    j = k;
+                },
+                _ if c == q => {
+                    if s.is_empty() {
+                        break;
+                    }
+                    return Ok((j + q.len_utf8(), s));
+                },
+                _ => {
+                    s.push(c);
+                    j += c.len_utf8();
+                }
+            }
+        }
+        Err(self.mk_error(YaccParserErrorKind::IllegalString, i))
+    }
+
+    /// Decode the escape sequence whose `\\` is at `i`, returning the offset after the escape
+    /// sequence and the character it denotes.
+    fn parse_escape(&self, i: usize) -> YaccResult<(usize, char)> {
+        debug_assert!(self.lookahead_is("\\", i).is_some());
+        let j = i + 1;
+        let c = match self.src[j..].chars().next() {
+            Some(c) => c,
+            None => return Err(self.mk_error(YaccParserErrorKind::IllegalEscape, i))
+        };
+        let d = match c {
+            '\\' | '\'' | '"' => c,
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                if let Some(caps) = RE_UNICODE_ESCAPE.captures(&self.src[j..]) {
+                    let k = j + caps.get(0).unwrap().end();
+                    if let Some(d) = u32::from_str_radix(&caps[1], 16).ok().and_then(char::from_u32) {
+                        return Ok((k, d));
+                    }
                 }
+                return Err(self.mk_error(YaccParserErrorKind::IllegalEscape, i));
             },
-            None => {
-                Err(self.mk_error(YaccParserErrorKind::IllegalString, i))
+            _ => return Err(self.mk_error(YaccParserErrorKind::IllegalEscape, i))
+        };
+        Ok((j + c.len_utf8(), d))
+    }
+
+    /// Is there an unquoted reference to the built-in `error` token (rather than a quoted
+    /// `'error'`, which is an ordinary token) at `i`?
+    fn is_error_term(&self, i: usize) -> bool {
+        match RE_NAME.find(&self.src[i..]) {
+            Some(m) => m.start() == 0 && m.as_str() == ERROR_TERM,
+            None => false
+        }
+    }
+
+    /// Parse a reference to a token starting at `i`. This is as `parse_terminal` except that, if
+    /// the token is a double-quoted alias of another token (declared with `%token NAME "alias"`),
+    /// the name of the aliased token is returned.
+    fn parse_token_ref(&self, i: usize) -> YaccResult<(usize, String)> {
+        let (j, n) = try!(self.parse_terminal(i));
+        if self.lookahead_is("\"", i).is_some() {
+            if let Some((m, _)) = self.ast.token_aliases.iter().find(|&(_, a)| *a == n) {
+                return Ok((j, m.clone()));
             }
         }
+        Ok((j, n))
     }
 
     fn parse_ws(&mut self, mut i: usize) -> YaccResult<usize> {
@@ -327,7 +1199,7 @@ impl YaccParser {
             match c {
                 ' '  | '\t' => i += c.len_utf8(),
                 '\n' | '\r' => {
-                    self.newlines.push(i + 1);
+                    self.add_newline(i + 1);
                     i += c.len_utf8();
                 },
                 '/' => {
@@ -342,7 +1214,7 @@ impl YaccParser {
                                 for c in self.src[i..].chars() {
                                     i += c.len_utf8();
                                     if c == '\n' || c == '\r' {
-                                        self.newlines.push(i);
+                                        self.add_newline(i);
                                         break;
                                     }
                                 }
@@ -356,7 +1228,7 @@ impl YaccParser {
                                     let c = self.src[k..].chars().nth(0).unwrap();
                                     k += c.len_utf8();
                                     match c {
-                                        '\n' | '\r' => self.newlines.push(i + 1),
+                                        '\n' | '\r' => self.add_newline(k),
                                         '*' => (),
                                         _ => continue
                                     }
@@ -384,6 +1256,14 @@ impl YaccParser {
         Ok(i)
     }
 
+    /// Record that a new line starts at `off`. Since, after an error, we may scan over the same
+    /// input more than once, newlines before the last one recorded are ignored.
+    fn add_newline(&mut self, off: usize) {
+        if off > *self.newlines.last().unwrap() {
+            self.newlines.push(off);
+        }
+    }
+
     fn lookahead_is(&self, s: &'static str, i: usize) -> Option<usize> {
         if self.src[i..].starts_with(s) {
             Some(i + s.len())
@@ -417,12 +1297,22 @@ impl YaccParser {
 
 #[cfg(test)]
 mod test {
+    use Span;
     use super::{YaccParser, YaccParserError, YaccParserErrorKind};
-    use yacc::{AssocKind, Precedence, YaccKind};
+    use yacc::{AssocKind, Precedence, YaccKind, YaccOptions};
     use yacc::ast::{GrammarAST, Production, Symbol};
 
     fn parse(yacc_kind: YaccKind, s: &str) -> Result<GrammarAST, YaccParserError> {
-        let mut yp = YaccParser::new(yacc_kind, s.to_string());
+        parse_all(yacc_kind, s).map_err(|mut errs| errs.remove(0))
+    }
+
+    fn parse_all(yacc_kind: YaccKind, s: &str) -> Result<GrammarAST, Vec<YaccParserError>> {
+        parse_with_options(yacc_kind, YaccOptions::default(), s)
+    }
+
+    fn parse_with_options(yacc_kind: YaccKind, options: YaccOptions, s: &str)
+                       -> Result<GrammarAST, Vec<YaccParserError>> {
+        let mut yp = YaccParser::new(yacc_kind, options, s.to_string());
         try!(yp.parse());
         Ok(yp.ast())
     }
@@ -458,7 +1348,14 @@ mod test {
                    vec![0]);
         assert_eq!(grm.prods[grm.get_rule("A").unwrap()[0]],
                    Production{symbols: vec![terminal("a")],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(32, 35),
+                              symbol_spans: vec![Span::new(32, 35)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None],
+                              name: None});
     }
 
     #[test]
@@ -471,10 +1368,24 @@ mod test {
         let grm = parse(YaccKind::Original, &src).unwrap();
         assert_eq!(grm.prods[grm.get_rule("A").unwrap()[0]],
                    Production{symbols: vec![terminal("a")],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(32, 35),
+                              symbol_spans: vec![Span::new(32, 35)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None],
+                              name: None});
         assert_eq!(grm.prods[grm.get_rule("A").unwrap()[1]],
                    Production{symbols: vec![terminal("b")],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(53, 56),
+                              symbol_spans: vec![Span::new(53, 56)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None],
+                              name: None});
     }
 
     #[test]
@@ -489,27 +1400,141 @@ mod test {
 
         assert_eq!(grm.prods[grm.get_rule("A").unwrap()[0]],
                    Production{symbols: vec![],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(32, 32),
+                              symbol_spans: vec![],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![],
+                              name: None});
 
         assert_eq!(grm.prods[grm.get_rule("B").unwrap()[0]],
                    Production{symbols: vec![terminal("b")],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(50, 53),
+                              symbol_spans: vec![Span::new(50, 53)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None],
+                              name: None});
         assert_eq!(grm.prods[grm.get_rule("B").unwrap()[1]],
                    Production{symbols: vec![],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(56, 56),
+                              symbol_spans: vec![],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![],
+                              name: None});
 
         assert_eq!(grm.prods[grm.get_rule("C").unwrap()[0]],
                    Production{symbols: vec![],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(74, 74),
+                              symbol_spans: vec![],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![],
+                              name: None});
         assert_eq!(grm.prods[grm.get_rule("C").unwrap()[1]],
                    Production{symbols: vec![terminal("c")],
-                              precedence: None});
-    }
-
-    #[test]
-    fn test_empty_program() {
-        let src = "%%\nA : 'a';\n%%".to_string();
-        parse(YaccKind::Original, &src).unwrap();
+                              precedence: None,
+                              action: None,
+                              span: Span::new(76, 79),
+                              symbol_spans: vec![Span::new(76, 79)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None],
+                              name: None});
+    }
+
+    #[test]
+    fn test_actions() {
+        let src = "
+            %%
+            A : 'a' B { $$ = $1 + $2; }
+              | { if x { y } else { z } }
+              | 'b' { let s = \"}{\"; let c = '}'; let d = '\\''; f::<'a>(s) }
+              | /* } */ { // }
+                          }
+              | 'c' { /* } \" */ } %prec 'c' ;
+        ";
+        let grm = parse(YaccKind::Original, &src).unwrap();
+        let action = |i: usize| grm.prods[grm.get_rule("A").unwrap()[i]].action.clone();
+        assert_eq!(grm.prods[grm.get_rule("A").unwrap()[0]],
+                   Production{symbols: vec![terminal("a"), nonterminal("B")],
+                              precedence: None,
+                              action: Some(" $$ = $1 + $2; ".to_string()),
+                              span: Span::new(32, 55),
+                              symbol_spans: vec![Span::new(32, 35), Span::new(36, 37)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None, None],
+                              name: None});
+        assert_eq!(action(1), Some(" if x { y } else { z } ".to_string()));
+        assert_eq!(action(2),
+                   Some(" let s = \"}{\"; let c = '}'; let d = '\\''; f::<'a>(s) ".to_string()));
+        assert_eq!(action(3), Some(" // }\n                          ".to_string()));
+        assert_eq!(action(4), Some(" /* } \" */ ".to_string()));
+        assert_eq!(grm.prods[grm.get_rule("A").unwrap()[4]].precedence, Some("c".to_string()));
+    }
+
+    #[test]
+    fn test_bad_actions() {
+        match parse(YaccKind::Original, &"
+          %%
+          A: 'a' { f(\"}\") ;
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IncompleteAction, line: 3, col: 18}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %%
+          A: 'a' { x } 'b';
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::MidRuleAction, line: 3, col: 24}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %%
+          A: 'a' { x } { y };
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::MidRuleAction, line: 3, col: 24}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        // Line numbers after a multi-line action are still correct.
+        match parse(YaccKind::Original, &"
+          %%
+          A: 'a' {
+            \"
+            \"
+          } | B:;
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IllegalString, line: 6, col: 16}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let src = "%%\nA : 'a';\n%%".to_string();
+        let grm = parse(YaccKind::Original, &src).unwrap();
+        assert_eq!(grm.programs, Some((src.len(), "".to_string())));
+
+        let src = "%%\nA : 'a';\n".to_string();
+        let grm = parse(YaccKind::Original, &src).unwrap();
+        assert_eq!(grm.programs, None);
     }
 
     #[test]
@@ -518,7 +1543,14 @@ mod test {
         let grm = parse(YaccKind::Original, &src).unwrap();
         assert_eq!(grm.prods[grm.get_rule("A").unwrap()[0]],
                    Production{symbols: vec![terminal("a"), nonterminal("B")],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(7, 12),
+                              symbol_spans: vec![Span::new(7, 10), Span::new(11, 12)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None, None],
+                              name: None});
     }
 
     #[test]
@@ -527,7 +1559,14 @@ mod test {
         let grm = parse(YaccKind::Original, &src).unwrap();
         assert_eq!(grm.prods[grm.get_rule("A").unwrap()[0]],
                    Production{symbols: vec![terminal("a"), terminal("b")],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(7, 14),
+                              symbol_spans: vec![Span::new(7, 10), Span::new(11, 14)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None, None],
+                              name: None});
     }
 
     #[test]
@@ -574,7 +1613,14 @@ mod test {
         assert!(grm.has_token("T"));
         assert_eq!(grm.prods[grm.get_rule("A").unwrap()[0]],
                    Production{symbols: vec![terminal("T")],
-                              precedence: None});
+                              precedence: None,
+                              action: None,
+                              span: Span::new(16, 17),
+                              symbol_spans: vec![Span::new(16, 17)],
+                              precedence_span: None,
+                              empty_span: None,
+                              labels: vec![None],
+                              name: None});
     }
 
     #[test]
@@ -604,6 +1650,194 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_unicode_names() {
+        let src = "%start Ausdruck\n%token Zahl café _x1\n%%\nAusdruck : Zahl Größe1 | café _x1;\nGröße1 : 'ü' ÿ;\nÿ: ;";
+        let grm = parse(YaccKind::Original, src).unwrap();
+        assert_eq!(grm.start, Some("Ausdruck".to_string()));
+        assert!(grm.has_token("café"));
+        assert_eq!(grm.prods[grm.get_rule("Ausdruck").unwrap()[0]].symbols,
+                   vec![terminal("Zahl"), nonterminal("Größe1")]);
+        assert_eq!(grm.prods[grm.get_rule("Ausdruck").unwrap()[1]].symbols,
+                   vec![terminal("café"), terminal("_x1")]);
+        assert_eq!(grm.prods[grm.get_rule("Größe1").unwrap()[0]].symbols,
+                   vec![terminal("ü"), nonterminal("ÿ")]);
+
+        let opts = YaccOptions{ascii_names: true, ..Default::default()};
+        assert!(parse_with_options(YaccKind::Original, opts, "%token Zahl\n%%\nA: Zahl B_2;\nB_2: ;").is_ok());
+        match parse_with_options(YaccKind::Original, opts, src) {
+            Err(ref errs) => {
+                let errs = errs.iter().map(|e| (format!("{:?}", e.kind), e.line, e.col))
+                                      .collect::<Vec<_>>();
+                assert_eq!(errs, vec![("IllegalString".to_string(), 2, 16),
+                                      ("IllegalString".to_string(), 4, 19),
+                                      ("MissingColon".to_string(), 5, 3),
+                                      ("IllegalName".to_string(), 6, 1)]);
+            },
+            Ok(_) => panic!()
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let src = "%%\nE: lhs=E '+' T[rhs] | x='('? E ')' | T;\nT: ;";
+        let ast = parse(YaccKind::Original, src).unwrap();
+        let prods = ast.get_rule("E").unwrap().iter().map(|&i| &ast.prods[i]).collect::<Vec<_>>();
+        assert_eq!(prods[0].symbols, vec![nonterminal("E"), terminal("+"), nonterminal("T")]);
+        assert_eq!(prods[0].labels, vec![Some("lhs".to_string()), None, Some("rhs".to_string())]);
+        // The span of a labelled symbol doesn't include its label.
+        assert_eq!(prods[0].symbol_spans, vec![Span::new(10, 11), Span::new(12, 15),
+                                               Span::new(16, 17)]);
+        assert_eq!(prods[0].span, Span::new(6, 22));
+        assert_eq!(prods[1].symbols, vec![nonterminal("'('?"), nonterminal("E"), terminal(")")]);
+        assert_eq!(prods[1].labels, vec![Some("x".to_string()), None, None]);
+        assert_eq!(prods[2].labels, vec![None]);
+
+        let src = "%%\nA: lhs = B rhs =\n  C D;\nB: ; C: ; D: ;";
+        let ast = parse(YaccKind::Original, src).unwrap();
+        let prod = &ast.prods[ast.get_rule("A").unwrap()[0]];
+        assert_eq!(prod.symbols, vec![nonterminal("B"), nonterminal("C"), nonterminal("D")]);
+        assert_eq!(prod.labels, vec![Some("lhs".to_string()), Some("rhs".to_string()), None]);
+        assert_eq!(prod.symbol_spans, vec![Span::new(12, 13), Span::new(22, 23), Span::new(24, 25)]);
+
+        for &(src, kind, col) in &[("%%\nA: x=B[y];", "MultipleLabels", 7),
+                                   ("%%\nA: B[y;", "IncompleteLabel", 5),
+                                   ("%%\nA: B['y'];", "IllegalName", 6),
+                                   ("%%\nA: B[];", "IllegalName", 6)] {
+            match parse(YaccKind::Original, src) {
+                Err(ref e) if format!("{:?}", e.kind) == kind && e.line == 2 && e.col == col => (),
+                Err(e) => panic!("Incorrect error returned {}", e),
+                Ok(_) => panic!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_production_names() {
+        let ast = parse(YaccKind::Original, "
+            %token '+'
+            %left '+'
+            %%
+            E: E '+' T { add } %prec '+' #Add | T #Term | %empty #Nothing | 'x';
+            T: 'id';
+            ").unwrap();
+        let names = ast.get_rule("E").unwrap()
+                                     .iter()
+                                     .map(|&i| ast.prods[i].name.clone())
+                                     .collect::<Vec<_>>();
+        assert_eq!(names, vec![Some("Add".to_string()), Some("Term".to_string()),
+                               Some("Nothing".to_string()), None]);
+
+        for &(src, kind, col) in &[("%%\nA: 'a' #X 'b';", "MisplacedProductionName", 11),
+                                   ("%%\nA: 'a' #X #Y;", "MisplacedProductionName", 11),
+                                   ("%%\nA: 'a' #X {x};", "MisplacedProductionName", 11),
+                                   ("%%\nA: 'a' # X;", "IllegalName", 9)] {
+            match parse(YaccKind::Original, src) {
+                Err(ref e) if format!("{:?}", e.kind) == kind && e.line == 2 && e.col == col => (),
+                Err(e) => panic!("Incorrect error returned {}", e),
+                Ok(_) => panic!()
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_token() {
+        let ast = parse(YaccKind::Original, "
+            %token error
+            %%
+            A: error ';' | 'error' | f(B);
+            B: 'b';
+            f(error): error;
+            ").unwrap();
+        assert!(ast.tokens.contains("error"));
+        let prods = ast.get_rule("A").unwrap().iter().map(|&i| &ast.prods[i]).collect::<Vec<_>>();
+        assert_eq!(prods[0].symbols, vec![Symbol::Error, terminal(";")]);
+        // A quoted `'error'` is an ordinary token.
+        assert_eq!(prods[1].symbols, vec![terminal("error")]);
+        // A parameter named `error` shadows the error terminal.
+        assert_eq!(ast.prods[ast.get_rule("f").unwrap()[0]].symbols, vec![nonterminal("error")]);
+
+        let ast = parse(YaccKind::Original, "%token error\n%%\nA: error;").unwrap();
+        assert!(!ast.tokens.contains("error"));
+        assert!(!ast.token_spans.contains_key("error"));
+
+        match parse(YaccKind::Original, "%%\nA: error;\nerror: 'a';") {
+            Err(YaccParserError{kind: YaccParserErrorKind::IllegalName, line: 3, col: 1}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+            Ok(_) => panic!()
+        }
+
+        let ast = parse(YaccKind::Original, "%token <T> 'error' 300\n%left 'error'\n%%\nA: 'error';")
+                      .unwrap();
+        assert!(ast.tokens.contains("error"));
+        assert_eq!(ast.types["error"], "T");
+        assert_eq!(ast.token_numbers["error"], 300);
+        assert!(ast.precs.contains_key("error"));
+
+        for &(src, col) in &[("%token error 300\n%%\nA: error;", 14),
+                             ("%token 'a' error \"ERR\"\n%%\nA: error;", 18),
+                             ("%token <T> error\n%%\nA: error;", 12),
+                             ("%left 'a' error\n%%\nA: error;", 11),
+                             ("%implicit_tokens ws error\n%%\nA: 'a';", 21)] {
+            match parse(YaccKind::Eco, src) {
+                Err(YaccParserError{kind: YaccParserErrorKind::IllegalErrorTokenDeclaration,
+                                    line: 1, col: c}) if c == col => (),
+                Err(e) => panic!("Incorrect error returned {}", e),
+                Ok(_) => panic!()
+            }
+        }
+        // The error token has no precedence, even if a quoted `'error'` does.
+        match parse(YaccKind::Original, "%left 'error'\n%%\nA: 'a' %prec error;") {
+            Err(YaccParserError{kind: YaccParserErrorKind::PrecNotFollowedByTerm, line: 3, col: 14}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+            Ok(_) => panic!()
+        }
+    }
+
+    #[test]
+    fn test_empty_marker() {
+        let src = "%%\nA: %empty | 'a' | %epsilon {x} | ;";
+        let ast = parse(YaccKind::Original, src).unwrap();
+        let prods = ast.get_rule("A").unwrap().iter().map(|&i| &ast.prods[i]).collect::<Vec<_>>();
+        assert!(prods[0].symbols.is_empty());
+        assert_eq!(prods[0].empty_span, Some(Span::new(6, 12)));
+        assert_eq!(prods[0].span, Span::new(6, 12));
+        assert_eq!(prods[1].empty_span, None);
+        assert!(prods[2].symbols.is_empty());
+        assert_eq!(prods[2].empty_span, Some(Span::new(21, 29)));
+        assert_eq!(prods[2].action, Some("x".to_string()));
+        assert!(prods[3].symbols.is_empty());
+        assert_eq!(prods[3].empty_span, None);
+        assert!(ast.warnings.is_empty());
+
+        let opts = YaccOptions{warn_implicit_empty: true, ..Default::default()};
+        let ast = parse_with_options(YaccKind::Original, opts, src).unwrap();
+        assert_eq!(ast.warnings.iter()
+                               .map(|w| (format!("{:?}", w.kind), w.line, w.col))
+                               .collect::<Vec<_>>(),
+                   vec![("ImplicitEmptyProduction".to_string(), 2, 34)]);
+        assert_eq!(ast.warnings[0].to_string(),
+                   "Empty production not marked with %empty at line 2 column 34");
+
+        match parse_all(YaccKind::Original, "%%\nA: 'a' %empty | %empty 'b' | %empty %empty;") {
+            Err(ref errs) => {
+                let errs = errs.iter().map(|e| (format!("{:?}", e.kind), e.line, e.col))
+                                      .collect::<Vec<_>>();
+                assert_eq!(errs, vec![("NonEmptyProduction".to_string(), 2, 8)]);
+            },
+            Ok(_) => panic!()
+        }
+        for &(src, col) in &[("%%\nA: %empty 'b';", 4), ("%%\nA: %empty %empty;", 11),
+                             ("%%\nA: %empty B;", 4)] {
+            match parse(YaccKind::Original, src) {
+                Err(YaccParserError{kind: YaccParserErrorKind::NonEmptyProduction, line: 2, col: c})
+                    if c == col => (),
+                Err(e) => panic!("Incorrect error returned {}", e),
+                Ok(_) => panic!()
+            }
+        }
+    }
+
     #[test]
     #[should_panic]
     fn test_simple_decl_fail() {
@@ -684,14 +1918,17 @@ A:
     }
 
     #[test]
-    fn test_programs_not_supported() {
+    fn test_programs() {
         let src = "%% %%
 x".to_string();
-        match parse(YaccKind::Original, &src) {
-            Ok(_)  => panic!("Programs parsed"),
-            Err(YaccParserError{kind: YaccParserErrorKind::ProgramsNotSupported, line: 1, col: 4}) => (),
-            Err(e) => panic!("Incorrect error returned {}", e)
-        }
+        let grm = parse(YaccKind::Original, &src).unwrap();
+        assert_eq!(grm.programs, Some((5, "\nx".to_string())));
+
+        let src = "%%\nA : 'a';\n%%\nfn main() { /* %% */ }\n".to_string();
+        let grm = parse(YaccKind::Original, &src).unwrap();
+        let (off, ref programs) = *grm.programs.as_ref().unwrap();
+        assert_eq!(programs, "\nfn main() { /* %% */ }\n");
+        assert_eq!(&src[off..], programs);
     }
 
     #[test]
@@ -862,6 +2099,556 @@ x".to_string();
         }
     }
 
+    #[test]
+    fn test_multiple_starts() {
+        let ast = parse(YaccKind::Original, &"
+          %start A B C
+          %%
+          ").unwrap();
+        assert_eq!(ast.start, Some("A".to_string()));
+        assert_eq!(ast.extra_starts, vec!["B".to_string(), "C".to_string()]);
+
+        match parse(YaccKind::Original, &"
+          %start A B A
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateStartDeclaration, line: 2, ..}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_expect() {
+        let ast = parse(YaccKind::Original, &"
+          %expect 2
+          %expect-rr 1
+          %%
+          R: 'a';
+          ").unwrap();
+        assert_eq!(ast.expect, Some(2));
+        assert_eq!(ast.expectrr, Some(1));
+
+        let ast = parse(YaccKind::Original, &"
+          %expect-rr 0
+          %%
+          R: 'a';
+          ").unwrap();
+        assert_eq!(ast.expect, None);
+        assert_eq!(ast.expectrr, Some(0));
+    }
+
+    #[test]
+    fn test_bad_expect() {
+        match parse(YaccKind::Original, &"
+          %expect 1
+          %expect 2
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateExpectDeclaration, line: 3, ..}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %expect-rr 1
+          %expect-rr 2
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateExpectRRDeclaration, line: 3, ..}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %expect x
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IllegalInteger, line: 2, col: 19}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_types() {
+        let ast = parse(YaccKind::Original, &"
+          %token <u32> a 'b'
+          %token c
+          %type <Vec<Box<u8>>> R S
+          %type <Box<Fn(u8) -> u8>> T
+          %type <fn(&str)->Option<u8>> U
+          %%
+          R: 'a';
+          ").unwrap();
+        assert_eq!(ast.types["a"], "u32");
+        assert_eq!(ast.types["b"], "u32");
+        assert!(!ast.types.contains_key("c"));
+        assert!(ast.tokens.contains("c"));
+        assert_eq!(ast.types["R"], "Vec<Box<u8>>");
+        assert_eq!(ast.types["S"], "Vec<Box<u8>>");
+        assert_eq!(ast.types["T"], "Box<Fn(u8) -> u8>");
+        assert_eq!(ast.types["U"], "fn(&str)->Option<u8>");
+    }
+
+    #[test]
+    fn test_bad_types() {
+        match parse(YaccKind::Original, &"
+          %type R
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::MissingType, line: 2, col: 17}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %type <> R
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::MissingType, line: 2, col: 17}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %token <Vec<u8> a
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IncompleteType, line: 2, col: 18}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_prologue_and_union() {
+        let ast = parse(YaccKind::Original, &"
+          %{
+          #include <stdio.h>
+          %}
+          %union { int i; struct { char *s; } s; }
+          %{ int x = 1; %}
+          %token a
+          %%
+          R: a;
+          ").unwrap();
+        assert_eq!(ast.prologues, vec!["
+          #include <stdio.h>
+          ".to_string(), " int x = 1; ".to_string()]);
+        assert_eq!(ast.union, Some(" int i; struct { char *s; } s; ".to_string()));
+        assert!(ast.tokens.contains("a"));
+
+        let ast = parse(YaccKind::Original, &"
+          %%
+          R: ;
+          ").unwrap();
+        assert!(ast.prologues.is_empty());
+        assert_eq!(ast.union, None);
+    }
+
+    #[test]
+    fn test_bad_prologue_and_union() {
+        match parse(YaccKind::Original, &"
+          %{
+          int x;
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IncompletePrologue, line: 2, col: 11}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %union { int i;
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IncompleteUnion, line: 2, col: 11}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %union int i;
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IncompleteUnion, line: 2, col: 11}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %union { int i; }
+          %union { int j; }
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateUnionDeclaration, line: 3, col: 11}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let src = "
+          %token a 'b' '+'
+          %left '+'
+          %%
+          R: a 'c' S { x } %prec '+'
+           | ;
+          S: ;
+          R: 'b';
+          ";
+        let ast = parse(YaccKind::Original, src).unwrap();
+        let span_str = |sp: Span| &src[sp.start()..sp.end()];
+        assert_eq!(span_str(ast.token_spans["a"]), "a");
+        assert_eq!(span_str(ast.token_spans["b"]), "'b'");
+        assert_eq!(span_str(ast.token_spans["c"]), "'c'");
+        assert_eq!(span_str(ast.prec_spans["+"]), "'+'");
+        assert_eq!(ast.token_spans["+"].start(), src.find("'+'").unwrap());
+        assert_eq!(span_str(ast.rule_spans["R"]), "R");
+        assert_eq!(ast.rule_spans["R"].start(), src.find("R:").unwrap());
+        assert_eq!(span_str(ast.rule_spans["S"]), "S");
+
+        let prod = &ast.prods[ast.rules["R"][0]];
+        assert_eq!(span_str(prod.span), "a 'c' S { x } %prec '+'");
+        assert_eq!(prod.symbol_spans.iter().map(|sp| span_str(*sp)).collect::<Vec<_>>(),
+                   vec!["a", "'c'", "S"]);
+        assert_eq!(span_str(prod.precedence_span.unwrap()), "'+'");
+        let prod = &ast.prods[ast.rules["R"][1]];
+        assert!(prod.span.is_empty());
+        assert_eq!(prod.span.start(), src.find("| ;").unwrap() + 2);
+        assert_eq!(prod.precedence_span, None);
+        let prod = &ast.prods[ast.rules["R"][2]];
+        assert_eq!(span_str(prod.span), "'b'");
+    }
+
+    #[test]
+    fn test_multiple_errors() {
+        let src = "
+          %token a 'b
+          %left '+'
+          %bogus
+          %%
+          A: 'a' { x; } 'b';
+          B: 'b' | 'c' :;
+          C: a;
+          D 'd';
+          E: /* ;
+             */ 'e' { f(\";\") } 'x';
+          ";
+        match parse_all(YaccKind::Original, src) {
+            Ok(_) => panic!(),
+            Err(errs) => {
+                let errs = errs.iter().map(|e| (format!("{:?}", e.kind), e.line, e.col))
+                                      .collect::<Vec<_>>();
+                assert_eq!(errs, vec![("IllegalString".to_string(), 2, 20),
+                                      ("UnknownDeclaration".to_string(), 4, 11),
+                                      ("MidRuleAction".to_string(), 6, 25),
+                                      ("IllegalString".to_string(), 7, 24),
+                                      ("MissingColon".to_string(), 9, 13),
+                                      ("MidRuleAction".to_string(), 11, 32)]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_token_numbers() {
+        let ast = parse(YaccKind::Original, &"
+          %token a 300 'b' c 258
+          %token <u8> d 0 e
+          %token a 300
+          %%
+          R: a;
+          ").unwrap();
+        assert_eq!(ast.token_numbers["a"], 300);
+        assert!(!ast.token_numbers.contains_key("b"));
+        assert_eq!(ast.token_numbers["c"], 258);
+        assert_eq!(ast.token_numbers["d"], 0);
+        assert!(!ast.token_numbers.contains_key("e"));
+        assert_eq!(ast.types["d"], "u8");
+        assert_eq!(ast.tokens.iter().collect::<Vec<_>>(), vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_bad_token_numbers() {
+        match parse(YaccKind::Original, &"
+          %token a 300 b 300
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateTokenNumber, line: 2, col: 26}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %token a 300
+          %token a 301
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateTokenNumber, line: 3, col: 20}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %token a 99999999999
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IllegalInteger, line: 2, col: 20}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_token_aliases() {
+        let ast = parse(YaccKind::Original, &"
+          %token PLUS \"+\" MINUS 259 \"-\" 'x' \"y\" TIMES
+          %left \"+\"
+          %type <u8> \"-\"
+          %%
+          R: R \"+\" R | R '+' R | R \"-\" R %prec \"+\" | TIMES | 'x';
+          ").unwrap();
+        assert_eq!(ast.token_aliases["PLUS"], "+");
+        assert_eq!(ast.token_aliases["MINUS"], "-");
+        assert!(!ast.token_aliases.contains_key("x"));
+        assert!(!ast.token_aliases.contains_key("TIMES"));
+        assert_eq!(ast.token_numbers["MINUS"], 259);
+        assert!(ast.precs.contains_key("PLUS"));
+        assert_eq!(ast.types["MINUS"], "u8");
+        assert_eq!(ast.tokens.iter().collect::<Vec<_>>(), vec!["PLUS", "MINUS", "x", "y", "TIMES", "+"]);
+        let rule = ast.get_rule("R").unwrap();
+        assert_eq!(ast.prods[rule[0]].symbols, vec![nonterminal("R"), terminal("PLUS"), nonterminal("R")]);
+        // Single-quoted terminals are never aliases.
+        assert_eq!(ast.prods[rule[1]].symbols, vec![nonterminal("R"), terminal("+"), nonterminal("R")]);
+        assert_eq!(ast.prods[rule[2]].symbols, vec![nonterminal("R"), terminal("MINUS"), nonterminal("R")]);
+        assert_eq!(ast.prods[rule[2]].precedence, Some("PLUS".to_string()));
+    }
+
+    #[test]
+    fn test_bad_token_aliases() {
+        match parse(YaccKind::Original, &"
+          %token A \"a\" B \"a\"
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateTokenAlias, line: 2, col: 26}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &"
+          %token A \"a\"
+          %token A \"b\"
+          %%
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::DuplicateTokenAlias, line: 3, col: 20}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_escapes() {
+        let ast = parse(YaccKind::Original, &r#"
+          %token '\'' "\"" '\\' 'a\nb' "\t" '\u{1F600}' "\u{e9}x"
+          %%
+          R: '\'' "\\" 'a\nb' "\u{1f600}" ;
+          "#).unwrap();
+        assert_eq!(ast.tokens.iter().collect::<Vec<_>>(),
+                   vec!["'", "\"", "\\", "a\nb", "\t", "\u{1F600}", "\u{e9}x"]);
+        assert_eq!(ast.prods[ast.get_rule("R").unwrap()[0]].symbols,
+                   vec![terminal("'"), terminal("\\"), terminal("a\nb"), terminal("\u{1F600}")]);
+    }
+
+    #[test]
+    fn test_bad_escapes() {
+        for &(src, col) in &[(r#"%token 'a\qb'"#, 10), (r#"%token "\u{110000}""#, 9),
+                             (r#"%token "\u{}""#, 9), (r#"%token "\u{1234567}""#, 9),
+                             (r#"%token '\u12'"#, 9), (r#"%token 'a\"#, 10)] {
+            match parse(YaccKind::Original, src) {
+                Ok(_) => panic!(),
+                Err(YaccParserError{kind: YaccParserErrorKind::IllegalEscape, line: 1, col: c})
+                    if c == col => (),
+                Err(e) => panic!("Incorrect error returned {}", e)
+            }
+        }
+
+        match parse(YaccKind::Original, &"%token ''") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IllegalString, line: 1, col: 8}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        match parse(YaccKind::Original, &r#"%token 'a\'"#) {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IllegalString, line: 1, col: 8}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_ebnf() {
+        let src = "
+          %token a
+          %%
+          R: a? 'b'* S+ (a 'c' | S)* (S);
+          S: a*;
+          ";
+        let ast = parse(YaccKind::Original, src).unwrap();
+        let rule_names = ast.rules.keys().map(|x| x.as_str()).collect::<Vec<_>>();
+        assert_eq!(rule_names, vec!["R", "'a'?", "'b'*", "S+", "('a' 'c' | S)", "('a' 'c' | S)*",
+                                    "S", "'a'*"]);
+        let prods = |n: &str| ast.rules[n].iter()
+                                          .map(|&p| ast.prods[p].symbols.clone())
+                                          .collect::<Vec<_>>();
+        let nonterm = |n: &str| Symbol::Nonterm(n.to_string());
+        let term = |n: &str| Symbol::Term(n.to_string());
+        assert_eq!(prods("R"), vec![vec![nonterm("'a'?"), nonterm("'b'*"), nonterm("S+"),
+                                         nonterm("('a' 'c' | S)*"), nonterm("S")]]);
+        assert_eq!(prods("'a'?"), vec![vec![term("a")], vec![]]);
+        assert_eq!(prods("'b'*"), vec![vec![], vec![nonterm("'b'*"), term("b")]]);
+        assert_eq!(prods("S+"), vec![vec![nonterm("S")], vec![nonterm("S+"), nonterm("S")]]);
+        assert_eq!(prods("('a' 'c' | S)"), vec![vec![term("a"), term("c")], vec![nonterm("S")]]);
+        assert_eq!(prods("('a' 'c' | S)*"), vec![vec![], vec![nonterm("('a' 'c' | S)*"),
+                                                              nonterm("('a' 'c' | S)")]]);
+        assert!(ast.synthetic_rules.contains("'a'*"));
+        assert!(!ast.synthetic_rules.contains("R"));
+        assert!(!ast.synthetic_rules.contains("S"));
+        assert_eq!(ast.synthetic_rules.len(), 6);
+
+        let span_str = |sp: Span| &src[sp.start()..sp.end()];
+        assert_eq!(span_str(ast.rule_spans["('a' 'c' | S)*"]), "(a 'c' | S)*");
+        assert_eq!(span_str(ast.rule_spans["('a' 'c' | S)"]), "(a 'c' | S)");
+        let prod = &ast.prods[ast.rules["R"][0]];
+        assert_eq!(prod.symbol_spans.iter().map(|sp| span_str(*sp)).collect::<Vec<_>>(),
+                   vec!["a?", "'b'*", "S+", "(a 'c' | S)*", "(S)"]);
+        let prod = &ast.prods[ast.rules["S+"][1]];
+        assert_eq!(span_str(prod.span), "S+");
+        assert_eq!(prod.symbol_spans.iter().map(|sp| span_str(*sp)).collect::<Vec<_>>(),
+                   vec!["S+", "S"]);
+
+        // Identical EBNF expressions share a single synthetic rule; operators can be stacked and
+        // whitespace is allowed before them.
+        let ast = parse(YaccKind::Original, "
+          %%
+          R: 'a'* | ('a') * | ( ) | ('a' |)+ ?;
+          ").unwrap();
+        let rule_names = ast.rules.keys().map(|x| x.as_str()).collect::<Vec<_>>();
+        assert_eq!(rule_names, vec!["R", "'a'*", "()", "('a' | )", "('a' | )+", "('a' | )+?"]);
+        assert_eq!(ast.prods[ast.rules["()"][0]].symbols, vec![]);
+    }
+
+    #[test]
+    fn test_bad_ebnf() {
+        match parse(YaccKind::Original, &"
+          %%
+          R: ('a' 'b';
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IncompleteGroup, line: 3, col: 14}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+        match parse(YaccKind::Original, &"
+          %%
+          R: ('a' { x } | 'b');
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::MidRuleAction, line: 3, col: 19}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+        match parse(YaccKind::Original, &"
+          %%
+          R: ('a' | (S 'b');
+          S: ;
+          ") {
+            Ok(_) => panic!(),
+            Err(YaccParserError{kind: YaccParserErrorKind::IncompleteGroup, line: 3, col: 14}) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_parameterised_rules() {
+        let src = "
+          %token a
+          %%
+          sep_list(sep, X): X | X sep sep_list(sep, X);
+          R: sep_list(',', a) opt(sep_list(a, R)) ;
+          opt(X): | X (',' X)*;
+          ";
+        let ast = parse(YaccKind::Original, src).unwrap();
+        assert_eq!(ast.start, Some("R".to_string()));
+        assert_eq!(ast.templates["sep_list"], vec!["sep".to_string(), "X".to_string()]);
+        assert_eq!(ast.templates["opt"], vec!["X".to_string()]);
+        let nonterm = |n: &str| Symbol::Nonterm(n.to_string());
+        let term = |n: &str| Symbol::Term(n.to_string());
+        let prods = |n: &str| ast.rules[n].iter()
+                                          .map(|&p| ast.prods[p].symbols.clone())
+                                          .collect::<Vec<_>>();
+        // Parameters are nonterminal references, even if (as `a` would be) they would otherwise
+        // be a token.
+        assert_eq!(prods("sep_list"), vec![vec![nonterm("X")],
+                                           vec![nonterm("X"), nonterm("sep"),
+                                                nonterm("sep_list(sep, X)")]]);
+        // Terminals are quoted in the names of applications, even if they weren't in the source.
+        assert_eq!(prods("R"), vec![vec![nonterm("sep_list(',', 'a')"),
+                                         nonterm("opt(sep_list('a', R))")]]);
+        assert_eq!(ast.applications["sep_list(sep, X)"],
+                   ("sep_list".to_string(), vec![nonterm("sep"), nonterm("X")]));
+        assert_eq!(ast.applications["sep_list(',', 'a')"],
+                   ("sep_list".to_string(), vec![term(","), term("a")]));
+        assert_eq!(ast.applications["opt(sep_list('a', R))"],
+                   ("opt".to_string(), vec![nonterm("sep_list('a', R)")]));
+        assert_eq!(ast.applications["sep_list('a', R)"],
+                   ("sep_list".to_string(), vec![term("a"), nonterm("R")]));
+
+        // Synthetic rules which refer to parameters are themselves parameterised.
+        assert_eq!(prods("opt"), vec![vec![], vec![nonterm("X"), nonterm("(',' X)*")]]);
+        assert_eq!(ast.templates["(',' X)"], vec!["X".to_string()]);
+        assert_eq!(ast.templates["(',' X)*"], vec!["X".to_string()]);
+        assert_eq!(ast.applications["(',' X)*"],
+                   ("(',' X)*".to_string(), vec![nonterm("X")]));
+        assert_eq!(prods("(',' X)*"), vec![vec![], vec![nonterm("(',' X)*"), nonterm("(',' X)")]]);
+        assert!(ast.synthetic_rules.contains("(',' X)*"));
+    }
+
+    #[test]
+    fn test_bad_parameterised_rules() {
+        let check = |src: &str, kind, line, col| {
+            match parse(YaccKind::Original, src) {
+                Ok(_) => panic!(),
+                Err(ref e) if format!("{:?}", e.kind) == kind && e.line == line && e.col == col => (),
+                Err(e) => panic!("Incorrect error returned {}", e)
+            }
+        };
+        check("%%\nR(X Y): X;", "IncompleteParameters", 2, 2);
+        check("%%\nR(X: X;", "IncompleteParameters", 2, 2);
+        check("%%\nR(X, X): X;", "DuplicateParameter", 2, 6);
+        check("%%\nR: S('a' 'b');", "IncompleteArguments", 2, 5);
+        check("%%\nR: S('a';", "IncompleteArguments", 2, 5);
+        check("%%\nR(X): X;\nS: ;\nR(Y): Y;", "MismatchedParameters", 4, 1);
+        check("%%\nR: ;\nR(X): X;", "MismatchedParameters", 3, 1);
+    }
+
+    #[test]
+    fn test_inline_rules() {
+        let src = "
+          %%
+          %inline op: '+' | '-';
+          E: E op E | 'x';
+          %inline
+            op2(X): X;
+          ";
+        let ast = parse(YaccKind::Original, src).unwrap();
+        assert_eq!(ast.start, Some("E".to_string()));
+        assert!(ast.inline_rules.contains("op"));
+        assert!(ast.inline_rules.contains("op2"));
+        assert!(!ast.inline_rules.contains("E"));
+        assert_eq!(ast.rule_spans["op"].start(), src.find("op:").unwrap());
+        assert_eq!(ast.rule_spans["op2"].start(), src.find("op2").unwrap());
+    }
+
     #[test]
     fn test_implicit_start() {
         let ast = parse(YaccKind::Eco, &"
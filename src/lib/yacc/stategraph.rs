@@ -0,0 +1,376 @@
+// Copyright (c) 2018 King's College London
+// created by the Software Development Team <http://soft-dev.org/>
+//
+// The Universal Permissive License (UPL), Version 1.0
+//
+// Subject to the condition set forth below, permission is hereby granted to any person obtaining a
+// copy of this software, associated documentation and/or data (collectively the "Software"), free
+// of charge and under any and all copyright rights in the Software, and any and all patent rights
+// owned or freely licensable by each licensor hereunder covering either (i) the unmodified
+// Software as contributed to or provided by such licensor, or (ii) the Larger Works (as defined
+// below), to deal in both
+//
+// (a) the Software, and
+// (b) any piece of software and/or hardware listed in the lrgrwrks.txt file
+// if one is included with the Software (each a "Larger Work" to which the Software is contributed
+// by such licensors),
+//
+// without restriction, including without limitation the rights to copy, create derivative works
+// of, display, perform, and distribute the Software and make, use, sell, offer for sale, import,
+// export, have made, and have sold the Software and the Larger Work(s), and to sublicense the
+// foregoing rights on either these or other terms.
+//
+// This license is subject to the following condition: The above copyright notice and either this
+// complete permission notice or at a minimum a reference to the UPL must be included in all copies
+// or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING
+// BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+// NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+// DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use indexmap::IndexMap;
+
+use {Grammar, PIdx, SIdx, StIdx, Symbol};
+use yacc::YaccGrammar;
+
+/// An LR(0) item: a production and the position of the "dot" within it (i.e. the index of the
+/// next symbol to be matched, which is equal to the production's length if the whole production
+/// has been matched).
+pub type Item = (PIdx, SIdx);
+
+/// A set of LR(0) items. Items are stored sorted and without duplicates, so two `Itemset`s are
+/// equal if, and only if, they contain the same items.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Itemset {
+    items: Vec<Item>
+}
+
+impl Itemset {
+    /// Create an itemset from `items` (which need not be sorted and may contain duplicates).
+    pub fn new(mut items: Vec<Item>) -> Itemset {
+        items.sort();
+        items.dedup();
+        Itemset{items}
+    }
+
+    /// Return the items in this itemset.
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// How many items does this itemset contain?
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Does this itemset contain no items?
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Does this itemset contain the item `item`?
+    pub fn contains(&self, item: Item) -> bool {
+        self.items.binary_search(&item).is_ok()
+    }
+
+    /// Return the closure of this itemset: for every item whose dot is before a nonterminal `N`,
+    /// the items with the dot at the start of each of `N`'s productions are added (recursively).
+    pub fn close(&self, grm: &YaccGrammar) -> Itemset {
+        let mut items = self.items.clone();
+        // Once we've added the items for a nonterminal, we never need to add them again.
+        let mut seen = vec![false; grm.nonterms_len() as usize];
+        let mut todo = self.items.clone();
+        while let Some((p_idx, s_idx)) = todo.pop() {
+            let nt_idx = match grm.prod(p_idx).get(usize::from(s_idx)) {
+                Some(&Symbol::Nonterm(nt_idx)) => nt_idx,
+                _ => continue
+            };
+            if seen[usize::from(nt_idx)] {
+                continue;
+            }
+            seen[usize::from(nt_idx)] = true;
+            for &o_p_idx in grm.nonterm_to_prods(nt_idx) {
+                let item = (o_p_idx, SIdx::from(0 as u32));
+                items.push(item);
+                todo.push(item);
+            }
+        }
+        Itemset::new(items)
+    }
+
+    /// Return the kernel of the itemset reached from this itemset when symbol `sym` is matched
+    /// (i.e. every item whose dot is before `sym`, with the dot moved over `sym`). `self` is
+    /// expected to be closed: items that would be added by closure are not considered.
+    pub fn goto(&self, grm: &YaccGrammar, sym: Symbol) -> Itemset {
+        let mut items = Vec::new();
+        for &(p_idx, s_idx) in &self.items {
+            if grm.prod(p_idx).get(usize::from(s_idx)) == Some(&sym) {
+                items.push((p_idx, SIdx::from(u32::from(s_idx) + 1)));
+            }
+        }
+        Itemset::new(items)
+    }
+}
+
+/// The canonical collection of LR(0) itemsets for a grammar, with edges between states labelled
+/// by the `Symbol` which is matched to move from one state to the next. There is a start state for
+/// each of the grammar's start productions: these are always the first states (i.e. the start
+/// state for the `i`th start production is `StIdx` `i`) and each consists of the single item
+/// `^: . S`. The start state for the grammar's main start rule is always `StIdx` 0.
+#[derive(Debug)]
+pub struct StateGraph {
+    /// A mapping from `StIdx` -> the kernel items of that state.
+    core_states: Vec<Itemset>,
+    /// A mapping from `StIdx` -> the closure of `core_states`.
+    closed_states: Vec<Itemset>,
+    /// A mapping from `StIdx` -> the outgoing edges of that state.
+    edges: Vec<IndexMap<Symbol, StIdx>>
+}
+
+impl StateGraph {
+    /// Build the canonical collection of LR(0) itemsets for `grm`.
+    pub fn new(grm: &YaccGrammar) -> StateGraph {
+        let mut core_states = Vec::new();
+        let mut closed_states = Vec::new();
+        let mut edges = Vec::new();
+        let mut states_map = HashMap::new();
+        for &p_idx in grm.start_prods() {
+            let start = Itemset::new(vec![(p_idx, SIdx::from(0 as u32))]);
+            states_map.insert(start.clone(), StIdx::from(core_states.len()));
+            core_states.push(start);
+        }
+
+        // States are processed in the order they're created, so the numbering of states is
+        // deterministic for a given grammar.
+        let mut i = 0;
+        while i < core_states.len() {
+            let closed = core_states[i].close(grm);
+            // Gather the symbols after the dot in the order we first see them.
+            let mut syms = Vec::new();
+            for &(p_idx, s_idx) in closed.items() {
+                if let Some(&sym) = grm.prod(p_idx).get(usize::from(s_idx)) {
+                    if !syms.contains(&sym) {
+                        syms.push(sym);
+                    }
+                }
+            }
+            let mut st_edges = IndexMap::new();
+            for sym in syms {
+                let nstate = closed.goto(grm, sym);
+                let st_idx = match states_map.get(&nstate) {
+                    Some(&st_idx) => st_idx,
+                    None => {
+                        let st_idx = StIdx::from(core_states.len());
+                        states_map.insert(nstate.clone(), st_idx);
+                        core_states.push(nstate);
+                        st_idx
+                    }
+                };
+                st_edges.insert(sym, st_idx);
+            }
+            closed_states.push(closed);
+            edges.push(st_edges);
+            i += 1;
+        }
+
+        StateGraph{core_states, closed_states, edges}
+    }
+
+    /// Create a state graph from its constituent parts. `core_states`, `closed_states`, and
+    /// `edges` must all be of the same length, and the start states must be at the beginning (in
+    /// the same order as the grammar's start productions).
+    pub(crate) fn from_parts(core_states: Vec<Itemset>,
+                             closed_states: Vec<Itemset>,
+                             edges: Vec<IndexMap<Symbol, StIdx>>) -> StateGraph
+    {
+        debug_assert!(core_states.len() == closed_states.len() && core_states.len() == edges.len());
+        StateGraph{core_states, closed_states, edges}
+    }
+
+    /// Return the index of the start state for the grammar's main start rule.
+    pub fn start_state(&self) -> StIdx {
+        StIdx::from(0 as u32)
+    }
+
+    /// Return the index of the start state for the start production `p_idx` (one of
+    /// [`YaccGrammar::start_prods`](../grammar/struct.YaccGrammar.html#method.start_prods)), or
+    /// `None` if `p_idx` is not a start production of `grm`.
+    pub fn start_state_for(&self, grm: &YaccGrammar, p_idx: PIdx) -> Option<StIdx> {
+        grm.start_prods().iter().position(|&x| x == p_idx).map(StIdx::from)
+    }
+
+    /// How many states does this graph have?
+    pub fn all_states_len(&self) -> u32 {
+        u32::try_from(self.core_states.len()).unwrap()
+    }
+
+    /// Return an iterator which produces (in order from `0..all_states_len()`) all this graph's
+    /// valid `StIdx`s.
+    pub fn iter_stidxs(&self) -> Box<dyn Iterator<Item=StIdx>> {
+        Box::new((0..self.all_states_len()).map(StIdx::from))
+    }
+
+    /// Return the kernel items of state `st_idx`. Panics if `st_idx` doesn't exist.
+    pub fn core_state(&self, st_idx: StIdx) -> &Itemset {
+        &self.core_states[usize::from(st_idx)]
+    }
+
+    /// Return the closed items of state `st_idx`. Panics if `st_idx` doesn't exist.
+    pub fn closed_state(&self, st_idx: StIdx) -> &Itemset {
+        &self.closed_states[usize::from(st_idx)]
+    }
+
+    /// Return the state reached from state `st_idx` by matching `sym`, or `None` if there is no
+    /// such edge. Panics if `st_idx` doesn't exist.
+    pub fn edge(&self, st_idx: StIdx, sym: Symbol) -> Option<StIdx> {
+        self.edges[usize::from(st_idx)].get(&sym).cloned()
+    }
+
+    /// Return all the outgoing edges of state `st_idx`. Panics if `st_idx` doesn't exist.
+    pub fn edges(&self, st_idx: StIdx) -> &IndexMap<Symbol, StIdx> {
+        &self.edges[usize::from(st_idx)]
+    }
+
+    /// How many edges does this graph have in total?
+    pub fn all_edges_len(&self) -> usize {
+        self.edges.iter().map(|x| x.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Itemset, StateGraph};
+    use {PIdx, SIdx, StIdx, Symbol};
+    use yacc::{yacc_grm, YaccGrammar, YaccKind};
+
+    // The classic expression grammar from the Dragon Book (p. 244 in the 2nd edition), which has
+    // 12 LR(0) states.
+    fn grammar() -> YaccGrammar {
+        yacc_grm(YaccKind::Original, "
+            %start E
+            %%
+            E: E '+' T | T;
+            T: T '*' F | F;
+            F: '(' E ')' | 'id';
+          ").unwrap()
+    }
+
+    fn nt(grm: &YaccGrammar, n: &str) -> Symbol {
+        Symbol::Nonterm(grm.nonterm_idx(n).unwrap())
+    }
+
+    fn t(grm: &YaccGrammar, n: &str) -> Symbol {
+        Symbol::Term(grm.term_idx(n).unwrap())
+    }
+
+    fn prod(grm: &YaccGrammar, n: &str, i: usize) -> PIdx {
+        grm.nonterm_to_prods(grm.nonterm_idx(n).unwrap())[i]
+    }
+
+    #[test]
+    fn test_itemset() {
+        let grm = grammar();
+        let s0 = SIdx::from(0 as u32);
+        let is = Itemset::new(vec![(prod(&grm, "T", 0), SIdx::from(2 as u32)),
+                                   (prod(&grm, "E", 1), s0),
+                                   (prod(&grm, "E", 1), s0)]);
+        assert_eq!(is.len(), 2);
+        assert!(is.contains((prod(&grm, "E", 1), s0)));
+        assert!(!is.contains((prod(&grm, "E", 0), s0)));
+        assert_eq!(is, Itemset::new(vec![(prod(&grm, "E", 1), s0),
+                                         (prod(&grm, "T", 0), SIdx::from(2 as u32))]));
+    }
+
+    #[test]
+    fn test_closure() {
+        let grm = grammar();
+        let s0 = SIdx::from(0 as u32);
+        let is = Itemset::new(vec![(grm.start_prod(), s0)]).close(&grm);
+        // ^: . E; E: . E '+' T; E: . T; T: . T '*' F; T: . F; F: . '(' E ')'; F: . 'id'
+        assert_eq!(is.len(), 7);
+        for p_idx in grm.nonterm_to_prods(grm.nonterm_idx("F").unwrap()) {
+            assert!(is.contains((*p_idx, s0)));
+        }
+
+        // Closing an itemset whose dots are all before terminals adds nothing.
+        let is = Itemset::new(vec![(prod(&grm, "T", 0), SIdx::from(1 as u32))]);
+        assert_eq!(is.close(&grm), is);
+    }
+
+    #[test]
+    fn test_goto() {
+        let grm = grammar();
+        let s0 = SIdx::from(0 as u32);
+        let s1 = SIdx::from(1 as u32);
+        let is = Itemset::new(vec![(grm.start_prod(), s0)]).close(&grm);
+        let e_is = is.goto(&grm, nt(&grm, "E"));
+        assert_eq!(e_is, Itemset::new(vec![(grm.start_prod(), s1), (prod(&grm, "E", 0), s1)]));
+        let t_is = is.goto(&grm, nt(&grm, "T"));
+        assert_eq!(t_is, Itemset::new(vec![(prod(&grm, "E", 1), s1), (prod(&grm, "T", 0), s1)]));
+        assert!(is.goto(&grm, t(&grm, "+")).is_empty());
+    }
+
+    #[test]
+    fn test_stategraph() {
+        let grm = grammar();
+        let sg = StateGraph::new(&grm);
+        assert_eq!(sg.all_states_len(), 12);
+        assert_eq!(sg.all_edges_len(), 22);
+        assert_eq!(sg.iter_stidxs().count(), 12);
+
+        let s0 = sg.start_state();
+        assert_eq!(sg.core_state(s0).len(), 1);
+        assert_eq!(sg.closed_state(s0).len(), 7);
+        assert_eq!(sg.edges(s0).len(), 5);
+        assert!(sg.edge(s0, t(&grm, "+")).is_none());
+
+        // Both '(' and 'id' are reachable from several states, but always lead to the same
+        // state.
+        let lparen = sg.edge(s0, t(&grm, "(")).unwrap();
+        let id = sg.edge(s0, t(&grm, "id")).unwrap();
+        assert_eq!(sg.edge(lparen, t(&grm, "(")), Some(lparen));
+        assert_eq!(sg.edge(lparen, t(&grm, "id")), Some(id));
+        assert_eq!(sg.core_state(id).items(), &[(prod(&grm, "F", 1), SIdx::from(1 as u32))]);
+        assert!(sg.edges(id).is_empty());
+
+        // E '+' T '*' F
+        let e = sg.edge(s0, nt(&grm, "E")).unwrap();
+        let plus = sg.edge(e, t(&grm, "+")).unwrap();
+        let pt = sg.edge(plus, nt(&grm, "T")).unwrap();
+        let star = sg.edge(pt, t(&grm, "*")).unwrap();
+        let f = sg.edge(star, nt(&grm, "F")).unwrap();
+        assert_eq!(sg.core_state(f).items(), &[(prod(&grm, "T", 0), SIdx::from(3 as u32))]);
+        assert_eq!(sg.edge(sg.edge(s0, nt(&grm, "T")).unwrap(), t(&grm, "*")), Some(star));
+        assert!(sg.iter_stidxs().all(|st_idx| st_idx < StIdx::from(12 as u32)));
+    }
+
+    #[test]
+    fn test_multiple_starts() {
+        let grm = yacc_grm(YaccKind::Original, "
+            %start P E
+            %%
+            P: P ';' E | E;
+            E: E '+' 'id' | 'id';
+          ").unwrap();
+        let sg = StateGraph::new(&grm);
+        let s0 = SIdx::from(0 as u32);
+        for (i, &p_idx) in grm.start_prods().iter().enumerate() {
+            let st_idx = sg.start_state_for(&grm, p_idx).unwrap();
+            assert_eq!(st_idx, StIdx::from(i));
+            assert_eq!(sg.core_state(st_idx).items(), &[(p_idx, s0)]);
+        }
+        assert_eq!(sg.start_state(), sg.start_state_for(&grm, grm.start_prod()).unwrap());
+        assert!(sg.start_state_for(&grm, prod(&grm, "E", 0)).is_none());
+
+        // From the expression start state, a ';' can never be shifted.
+        let e_st = sg.start_state_for(&grm, grm.start_prods()[1]).unwrap();
+        let after_e = sg.edge(e_st, nt(&grm, "E")).unwrap();
+        assert!(sg.edge(after_e, t(&grm, ";")).is_none());
+        assert!(sg.edge(after_e, t(&grm, "+")).is_some());
+    }
+}